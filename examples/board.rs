@@ -0,0 +1,85 @@
+use bevy::prelude::*;
+use bevy::render::camera::RenderTarget;
+use bevy_sprite_tilemap::prelude::*;
+use bevy_sprite_tilemap::util::pick_tile;
+
+// A board with gaps between its squares and a border around it.
+// Hovering over a square highlights it, hovering over a gap highlights nothing.
+
+fn spawn_board(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut texture_atlases: ResMut<Assets<TextureAtlas>>,
+) {
+    let tile_size = 16.0 * Vec2::ONE;
+    let texture_atlas_image = asset_server.load("test_tileset.png");
+    let texture_atlas = TextureAtlas::from_grid(texture_atlas_image, tile_size, 4, 4, None, None);
+    commands.spawn(TextureAtlasTilemapBundle {
+        tilemap: Tilemap::from_fn(8, 8, |x, y| TextureAtlasTile::new((x + y) % 2)),
+        geometry: TilemapGeometry {
+            tile_size,
+            spacing: 4. * Vec2::ONE,
+            margin: 12. * Vec2::ONE,
+            ..Default::default()
+        },
+        texture_atlas: texture_atlases.add(texture_atlas),
+        transform: Transform::from_scale(Vec3::splat(3.)),
+        ..Default::default()
+    });
+}
+
+fn highlight(
+    windows: Res<Windows>,
+    camera_query: Query<(&Camera, &GlobalTransform), With<Camera2d>>,
+    mut tilemap_query: Query<(
+        &mut Tilemap<TextureAtlasTile>,
+        &TilemapGeometry,
+        &TilemapView,
+        &GlobalTransform,
+    )>,
+) {
+    let (camera, camera_transform) = camera_query.single();
+    let window = match camera.target {
+        RenderTarget::Window(id) => windows.get(id),
+        _ => windows.get_primary(),
+    };
+    let world_point = window.and_then(|window| {
+        let cursor = window.cursor_position()?;
+        let window_size = Vec2::new(window.width(), window.height());
+        let ndc = (cursor / window_size) * 2.0 - Vec2::ONE;
+        let ndc_to_world = camera_transform.compute_matrix() * camera.projection_matrix().inverse();
+        Some(ndc_to_world.project_point3(ndc.extend(-1.0)).truncate())
+    });
+    for (mut tilemap, geometry, view, transform) in tilemap_query.iter_mut() {
+        let picked = world_point.and_then(|point| {
+            pick_tile(
+                point,
+                transform,
+                tilemap.width(),
+                tilemap.height(),
+                geometry,
+                view,
+                None,
+            )
+        });
+        for (x, y, tile) in tilemap.indexed_iter_mut() {
+            tile.color = if picked == Some([x, y]) {
+                Color::WHITE
+            } else {
+                Color::GRAY
+            };
+        }
+    }
+}
+
+fn main() {
+    App::new()
+        .add_plugins(DefaultPlugins)
+        .add_plugin(SpriteTilemapPlugin)
+        .add_startup_system(|mut commands: Commands| {
+            commands.spawn(Camera2dBundle::default());
+        })
+        .add_startup_system(spawn_board)
+        .add_system(highlight)
+        .run();
+}
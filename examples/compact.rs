@@ -0,0 +1,67 @@
+use bevy::diagnostic::{FrameTimeDiagnosticsPlugin, LogDiagnosticsPlugin};
+use bevy::prelude::*;
+use bevy_sprite_tilemap::prelude::*;
+
+// A million cell tilemap of four byte tiles
+
+fn spawn_grid(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut texture_atlases: ResMut<Assets<TextureAtlas>>,
+) {
+    let tile_size = 16.0 * Vec2::ONE;
+    let texture_atlas_image = asset_server.load("test_tileset.png");
+    let texture_atlas = TextureAtlas::from_grid(texture_atlas_image, tile_size, 4, 4, None, None);
+    let tilemap = Tilemap::from_fn(1000, 1000, |x, y| {
+        CompactTile::new(((x + y) % 16) as u16)
+            .with_tint((x / 10 % 3) as u8)
+            .with_orientation(Orientation::ALL[(x * 7 + y * 3) % 8])
+    });
+    commands.spawn(CompactTilemapBundle {
+        tilemap,
+        geometry: TilemapGeometry {
+            tile_size,
+            ..Default::default()
+        },
+        texture_atlas: texture_atlases.add(texture_atlas),
+        palette: TilePalette::new(vec![Color::WHITE, Color::ORANGE, Color::CYAN]),
+        ..Default::default()
+    });
+}
+
+fn move_camera(
+    time: Res<Time>,
+    keyboard_input: Res<Input<KeyCode>>,
+    mut camera_query: Query<&mut Transform, With<Camera>>,
+) {
+    let mut direction = Vec2::ZERO;
+    if keyboard_input.pressed(KeyCode::Left) {
+        direction.x -= 1.;
+    }
+    if keyboard_input.pressed(KeyCode::Right) {
+        direction.x += 1.;
+    }
+    if keyboard_input.pressed(KeyCode::Down) {
+        direction.y -= 1.;
+    }
+    if keyboard_input.pressed(KeyCode::Up) {
+        direction.y += 1.;
+    }
+    for mut camera_transform in camera_query.iter_mut() {
+        camera_transform.translation += (1000. * time.delta_seconds() * direction).extend(0.);
+    }
+}
+
+fn main() {
+    App::new()
+        .add_plugins(DefaultPlugins)
+        .add_plugin(LogDiagnosticsPlugin::default())
+        .add_plugin(FrameTimeDiagnosticsPlugin::default())
+        .add_plugin(SpriteTilemapPlugin)
+        .add_startup_system(|mut commands: Commands| {
+            commands.spawn(Camera2dBundle::default());
+        })
+        .add_startup_system(spawn_grid)
+        .add_system(move_camera)
+        .run();
+}
@@ -0,0 +1,68 @@
+use bevy::prelude::*;
+use bevy_sprite_tilemap::prelude::*;
+use std::borrow::Cow;
+
+/// Gameplay tile data drawn directly, without a separate tilemap of `TextureAtlasTile`s
+#[derive(Clone, Default)]
+enum Terrain {
+    #[default]
+    Empty,
+    Grass,
+    Water {
+        depth: usize,
+    },
+}
+
+impl Tileable for Terrain {}
+
+impl AsAtlasTile for Terrain {
+    fn as_atlas_tile(&self) -> Option<Cow<'_, TextureAtlasTile>> {
+        match *self {
+            Terrain::Empty => None,
+            Terrain::Grass => Some(Cow::Owned(TextureAtlasTile::new(5))),
+            Terrain::Water { depth } => Some(Cow::Owned(TextureAtlasTile {
+                index: 10,
+                color: Color::rgb(1.0, 1.0, 1.0 - 0.2 * depth as f32),
+                ..Default::default()
+            })),
+        }
+    }
+}
+
+fn spawn_grid(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut texture_atlases: ResMut<Assets<TextureAtlas>>,
+) {
+    let tile_size = 16.0 * Vec2::ONE;
+    let texture_atlas_image = asset_server.load("test_tileset.png");
+    let texture_atlas = TextureAtlas::from_grid(texture_atlas_image, tile_size, 4, 4, None, None);
+    let tilemap = Tilemap::from_fn(12, 8, |x, y| match (x + y) % 5 {
+        0 => Terrain::Empty,
+        1 | 2 => Terrain::Grass,
+        depth => Terrain::Water { depth },
+    });
+
+    commands.spawn((
+        tilemap,
+        TilemapGeometry {
+            tile_size: 2. * tile_size,
+            ..Default::default()
+        },
+        TilemapView::All,
+        texture_atlases.add(texture_atlas),
+        SpatialBundle::default(),
+    ));
+}
+
+fn main() {
+    App::new()
+        .add_plugins(DefaultPlugins)
+        .add_plugin(SpriteTilemapPlugin)
+        .add_plugin(ExtractAtlasTilemapPlugin::<Tilemap<Terrain>>::default())
+        .add_startup_system(|mut commands: Commands| {
+            commands.spawn(Camera2dBundle::default());
+        })
+        .add_startup_system(spawn_grid)
+        .run();
+}
@@ -0,0 +1,79 @@
+use bevy::prelude::*;
+use bevy::sprite::Anchor;
+use bevy_sprite_tilemap::prelude::*;
+
+// The debug overlay drawn over a tilemap.
+// Press A to cycle the anchor, R and C to reverse the rows and columns,
+// V to switch between drawing a section and the whole tilemap, and F3 to toggle the overlay.
+
+const ANCHORS: [Anchor; 5] = [
+    Anchor::Center,
+    Anchor::BottomLeft,
+    Anchor::TopLeft,
+    Anchor::TopRight,
+    Anchor::BottomRight,
+];
+
+fn spawn_tilemap(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut texture_atlases: ResMut<Assets<TextureAtlas>>,
+) {
+    let tile_size = 16.0 * Vec2::ONE;
+    let texture_atlas_image = asset_server.load("test_tileset.png");
+    let texture_atlas = TextureAtlas::from_grid(texture_atlas_image, tile_size, 4, 4, None, None);
+    commands.spawn(TextureAtlasTilemapBundle {
+        tilemap: Tilemap::from_fn(6, 4, |x, y| TextureAtlasTile::new(x + 4 * (y % 4))),
+        geometry: TilemapGeometry {
+            tile_size,
+            margin: 4. * Vec2::ONE,
+            ..Default::default()
+        },
+        texture_atlas: texture_atlases.add(texture_atlas),
+        transform: Transform::from_scale(Vec3::splat(4.)),
+        ..Default::default()
+    });
+}
+
+fn control(
+    keyboard: Res<Input<KeyCode>>,
+    mut anchor_index: Local<usize>,
+    mut query: Query<(&mut TilemapGeometry, &mut TilemapView)>,
+) {
+    for (mut geometry, mut view) in query.iter_mut() {
+        if keyboard.just_pressed(KeyCode::A) {
+            *anchor_index = (*anchor_index + 1) % ANCHORS.len();
+            geometry.anchor = ANCHORS[*anchor_index].clone();
+        }
+        if keyboard.just_pressed(KeyCode::R) {
+            geometry.reverse_rows = !geometry.reverse_rows;
+        }
+        if keyboard.just_pressed(KeyCode::C) {
+            geometry.reverse_columns = !geometry.reverse_columns;
+        }
+        if keyboard.just_pressed(KeyCode::V) {
+            *view = match *view {
+                TilemapView::All => TilemapView::Section {
+                    x: 1,
+                    y: 1,
+                    width: 3,
+                    height: 2,
+                },
+                _ => TilemapView::All,
+            };
+        }
+    }
+}
+
+fn main() {
+    App::new()
+        .add_plugins(DefaultPlugins)
+        .add_plugin(SpriteTilemapPlugin)
+        .add_plugin(TilemapDebugPlugin)
+        .add_startup_system(|mut commands: Commands| {
+            commands.spawn(Camera2dBundle::default());
+        })
+        .add_startup_system(spawn_tilemap)
+        .add_system(control)
+        .run();
+}
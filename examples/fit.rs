@@ -0,0 +1,56 @@
+use bevy::prelude::*;
+use bevy_sprite_tilemap::prelude::*;
+
+// Images of different sizes drawn on one grid.
+// Press Space to cycle through the fit modes.
+
+const FITS: [TileFit; 4] = [
+    TileFit::Native,
+    TileFit::Stretch,
+    TileFit::Contain,
+    TileFit::Cover,
+];
+
+fn spawn_tilemap(mut commands: Commands, asset_server: Res<AssetServer>) {
+    let textures: Vec<Handle<Image>> = ["a.png", "b.png", "c.png", "d.png", "test_tileset.png"]
+        .into_iter()
+        .map(|path| asset_server.load(path))
+        .collect();
+    commands.spawn(SpriteTilemapBundle {
+        tilemap: Tilemap::from_fn(5, 4, |x, y| {
+            SpriteTile::new(textures[(x + y) % textures.len()].clone())
+        }),
+        geometry: TilemapGeometry {
+            tile_size: Vec2::new(96., 64.),
+            spacing: 8. * Vec2::ONE,
+            ..Default::default()
+        },
+        ..Default::default()
+    });
+}
+
+fn cycle_fit(keyboard: Res<Input<KeyCode>>, mut query: Query<&mut TilemapGeometry>) {
+    if keyboard.just_pressed(KeyCode::Space) {
+        for mut geometry in query.iter_mut() {
+            let next = FITS
+                .iter()
+                .position(|&fit| fit == geometry.fit)
+                .unwrap_or(0)
+                + 1;
+            geometry.fit = FITS[next % FITS.len()];
+            info!("fit: {:?}", geometry.fit);
+        }
+    }
+}
+
+fn main() {
+    App::new()
+        .add_plugins(DefaultPlugins)
+        .add_plugin(SpriteTilemapPlugin)
+        .add_startup_system(|mut commands: Commands| {
+            commands.spawn(Camera2dBundle::default());
+        })
+        .add_startup_system(spawn_tilemap)
+        .add_system(cycle_fit)
+        .run();
+}
@@ -0,0 +1,86 @@
+use bevy::prelude::*;
+use bevy_sprite_tilemap::prelude::*;
+
+// A sprite moving smoothly from cell to cell over a rotated tilemap.
+// Use the arrow keys to move.
+
+fn setup(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut texture_atlases: ResMut<Assets<TextureAtlas>>,
+) {
+    commands.spawn(Camera2dBundle::default());
+    let tile_size = 16.0 * Vec2::ONE;
+    let texture_atlas_image = asset_server.load("test_tileset.png");
+    let texture_atlas = TextureAtlas::from_grid(texture_atlas_image, tile_size, 4, 4, None, None);
+    let tilemap = commands
+        .spawn(TextureAtlasTilemapBundle {
+            tilemap: Tilemap::from_fn(10, 8, |x, y| TextureAtlasTile::new((x + y) % 2)),
+            geometry: TilemapGeometry {
+                tile_size,
+                ..Default::default()
+            },
+            texture_atlas: texture_atlases.add(texture_atlas),
+            transform: Transform {
+                rotation: Quat::from_rotation_z(0.3),
+                scale: Vec3::splat(3.),
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .id();
+    commands.spawn((
+        SpriteBundle {
+            sprite: Sprite {
+                color: Color::ORANGE,
+                custom_size: Some(Vec2::splat(24.)),
+                ..Default::default()
+            },
+            transform: Transform::from_xyz(0., 0., 1.),
+            ..Default::default()
+        },
+        TilePosition::new(tilemap, [0, 0]),
+        TileMovement::new(6.),
+    ));
+}
+
+fn control(
+    keyboard: Res<Input<KeyCode>>,
+    tilemap_query: Query<&Tilemap<TextureAtlasTile>>,
+    mut query: Query<(&mut TilePosition, &TileMovement)>,
+) {
+    for (mut position, movement) in query.iter_mut() {
+        if movement.is_moving(&position) {
+            continue;
+        }
+        let tilemap = match tilemap_query.get(position.tilemap) {
+            Ok(tilemap) => tilemap,
+            Err(_) => continue,
+        };
+        let [x, y] = position.cell;
+        let next = if keyboard.pressed(KeyCode::Left) {
+            [x.saturating_sub(1), y]
+        } else if keyboard.pressed(KeyCode::Right) {
+            [(x + 1).min(tilemap.width() - 1), y]
+        } else if keyboard.pressed(KeyCode::Down) {
+            [x, y.saturating_sub(1)]
+        } else if keyboard.pressed(KeyCode::Up) {
+            [x, (y + 1).min(tilemap.height() - 1)]
+        } else {
+            continue;
+        };
+        if next != position.cell {
+            position.cell = next;
+        }
+    }
+}
+
+fn main() {
+    App::new()
+        .add_plugins(DefaultPlugins)
+        .add_plugin(SpriteTilemapPlugin)
+        .add_plugin(TileMovementPlugin)
+        .add_startup_system(setup)
+        .add_system(control)
+        .run();
+}
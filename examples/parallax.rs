@@ -0,0 +1,80 @@
+use bevy::prelude::*;
+use bevy_sprite_tilemap::prelude::*;
+
+fn spawn_layers(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut texture_atlases: ResMut<Assets<TextureAtlas>>,
+) {
+    let tile_size = 16.0 * Vec2::ONE;
+    let texture_atlas_image = asset_server.load("test_tileset.png");
+    let texture_atlas = TextureAtlas::from_grid(texture_atlas_image, tile_size, 4, 4, None, None);
+    let texture_atlas_handle = texture_atlases.add(texture_atlas);
+
+    for (layer, (factor, color)) in [
+        (0.25, Color::DARK_GRAY),
+        (0.5, Color::GRAY),
+        (1.0, Color::WHITE),
+    ]
+    .into_iter()
+    .enumerate()
+    {
+        let tilemap = Tilemap::from_fn(8, 8, |x, y| {
+            ((x + layer) % 3 == 0 && (y + 2 * layer) % 4 == 0).then(|| TextureAtlasTile {
+                index: (x + y) % 16,
+                color,
+                ..Default::default()
+            })
+        });
+        commands.spawn((
+            SparseAtlasTilemapBundle {
+                tilemap,
+                geometry: TilemapGeometry {
+                    tile_size,
+                    ..Default::default()
+                },
+                texture_atlas: texture_atlas_handle.clone(),
+                transform: Transform::from_translation(layer as f32 * Vec3::Z),
+                ..Default::default()
+            },
+            TilemapParallax {
+                factor: factor * Vec2::ONE,
+                repeat: true,
+                ..Default::default()
+            },
+        ));
+    }
+}
+
+fn move_camera(
+    time: Res<Time>,
+    keyboard: Res<Input<KeyCode>>,
+    mut camera_query: Query<&mut Transform, With<Camera>>,
+) {
+    let mut direction = Vec2::ZERO;
+    for (key, d) in [
+        (KeyCode::Left, -Vec2::X),
+        (KeyCode::Right, Vec2::X),
+        (KeyCode::Down, -Vec2::Y),
+        (KeyCode::Up, Vec2::Y),
+    ] {
+        if keyboard.pressed(key) {
+            direction += d;
+        }
+    }
+    for mut transform in camera_query.iter_mut() {
+        transform.translation += (200. * time.delta_seconds() * direction).extend(0.);
+    }
+}
+
+fn main() {
+    App::new()
+        .add_plugins(DefaultPlugins)
+        .add_plugin(SpriteTilemapPlugin)
+        .add_startup_system(|mut commands: Commands| {
+            commands.spawn(Camera2dBundle::default());
+        })
+        .add_startup_system(spawn_layers)
+        .add_system(move_camera)
+        .run();
+}
@@ -0,0 +1,67 @@
+use bevy::prelude::*;
+use bevy_sprite_tilemap::prelude::*;
+
+fn spawn_grid(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut texture_atlases: ResMut<Assets<TextureAtlas>>,
+) {
+    let tile_size = 16.0 * Vec2::ONE;
+    let tilemap = Tilemap::from_fn(64, 64, |x, y| TextureAtlasTile::new((x * 7 + y * 3) % 16));
+    let texture_atlas_image = asset_server.load("test_tileset.png");
+    let texture_atlas = TextureAtlas::from_grid(texture_atlas_image, tile_size, 4, 4, None, None);
+
+    commands.spawn(TextureAtlasTilemapBundle {
+        tilemap,
+        geometry: TilemapGeometry {
+            tile_size,
+            ..Default::default()
+        },
+        view: TilemapView::Scroll {
+            x: 0.,
+            y: 0.,
+            width: 16,
+            height: 12,
+            wrap: false,
+        },
+        texture_atlas: texture_atlases.add(texture_atlas),
+        ..Default::default()
+    });
+}
+
+fn scroll(
+    time: Res<Time>,
+    keyboard: Res<Input<KeyCode>>,
+    mut tilemap_query: Query<&mut TilemapView>,
+) {
+    let mut direction = Vec2::ZERO;
+    for (key, d) in [
+        (KeyCode::Left, -Vec2::X),
+        (KeyCode::Right, Vec2::X),
+        (KeyCode::Down, -Vec2::Y),
+        (KeyCode::Up, Vec2::Y),
+    ] {
+        if keyboard.pressed(key) {
+            direction += d;
+        }
+    }
+    let delta = 4. * time.delta_seconds() * direction;
+    for mut view in tilemap_query.iter_mut() {
+        if let TilemapView::Scroll { x, y, .. } = &mut *view {
+            *x += delta.x;
+            *y += delta.y;
+        }
+    }
+}
+
+fn main() {
+    App::new()
+        .add_plugins(DefaultPlugins)
+        .add_plugin(SpriteTilemapPlugin)
+        .add_startup_system(|mut commands: Commands| {
+            commands.spawn(Camera2dBundle::default());
+        })
+        .add_startup_system(spawn_grid)
+        .add_system(scroll)
+        .run();
+}
@@ -0,0 +1,117 @@
+use bevy::core_pipeline::clear_color::ClearColorConfig;
+use bevy::prelude::*;
+use bevy::render::camera::Viewport;
+use bevy::render::view::RenderLayers;
+use bevy_sprite_tilemap::prelude::*;
+
+// One tilemap drawn by two view entities, each seen by its own camera
+// in one half of the window. Use WASD and the arrow keys to scroll the two views.
+
+#[derive(Component)]
+struct Player(usize);
+
+fn setup(
+    mut commands: Commands,
+    windows: Res<Windows>,
+    asset_server: Res<AssetServer>,
+    mut texture_atlases: ResMut<Assets<TextureAtlas>>,
+) {
+    let tile_size = 16.0 * Vec2::ONE;
+    let texture_atlas_image = asset_server.load("test_tileset.png");
+    let texture_atlas = TextureAtlas::from_grid(texture_atlas_image, tile_size, 4, 4, None, None);
+    let tilemap = commands
+        .spawn(TextureAtlasTilemapBundle {
+            tilemap: Tilemap::from_fn(64, 64, |x, y| TextureAtlasTile::new((x * 7 + y * 3) % 16)),
+            texture_atlas: texture_atlases.add(texture_atlas),
+            visibility: Visibility { is_visible: false },
+            ..Default::default()
+        })
+        .id();
+
+    let window = windows.primary();
+    let half_size = UVec2::new(window.physical_width() / 2, window.physical_height());
+    for player in 0..2 {
+        let layer = RenderLayers::layer(1 + player as u8);
+        commands.spawn((
+            Camera2dBundle {
+                camera: Camera {
+                    priority: player as isize,
+                    viewport: Some(Viewport {
+                        physical_position: UVec2::new(player as u32 * half_size.x, 0),
+                        physical_size: half_size,
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                },
+                camera_2d: Camera2d {
+                    clear_color: if player == 0 {
+                        ClearColorConfig::Default
+                    } else {
+                        ClearColorConfig::None
+                    },
+                },
+                ..Default::default()
+            },
+            layer,
+        ));
+        commands.spawn((
+            TilemapViewBundle {
+                geometry: TilemapGeometry {
+                    tile_size,
+                    ..Default::default()
+                },
+                view: TilemapView::Scroll {
+                    x: 16. * player as f32,
+                    y: 16. * player as f32,
+                    width: 24,
+                    height: 24,
+                    wrap: true,
+                },
+                transform: Transform::from_scale(Vec3::splat(2.)),
+                ..TilemapViewBundle::new(tilemap)
+            },
+            layer,
+            Player(player),
+        ));
+    }
+}
+
+fn scroll(
+    time: Res<Time>,
+    keyboard: Res<Input<KeyCode>>,
+    mut query: Query<(&Player, &mut TilemapView)>,
+) {
+    const KEYS: [[KeyCode; 4]; 2] = [
+        [KeyCode::A, KeyCode::D, KeyCode::S, KeyCode::W],
+        [KeyCode::Left, KeyCode::Right, KeyCode::Down, KeyCode::Up],
+    ];
+    for (player, mut view) in query.iter_mut() {
+        let [left, right, down, up] = KEYS[player.0];
+        let mut direction = Vec2::ZERO;
+        if keyboard.pressed(left) {
+            direction.x -= 1.;
+        }
+        if keyboard.pressed(right) {
+            direction.x += 1.;
+        }
+        if keyboard.pressed(down) {
+            direction.y -= 1.;
+        }
+        if keyboard.pressed(up) {
+            direction.y += 1.;
+        }
+        if let TilemapView::Scroll { x, y, .. } = &mut *view {
+            *x = (*x + 8. * direction.x * time.delta_seconds()).max(0.);
+            *y = (*y + 8. * direction.y * time.delta_seconds()).max(0.);
+        }
+    }
+}
+
+fn main() {
+    App::new()
+        .add_plugins(DefaultPlugins)
+        .add_plugin(SpriteTilemapPlugin)
+        .add_startup_system(setup)
+        .add_system(scroll)
+        .run();
+}
@@ -0,0 +1,78 @@
+use bevy::prelude::*;
+use bevy::render::camera::RenderTarget;
+use bevy_sprite_tilemap::prelude::*;
+
+// A spreadsheet-like grid whose columns and rows each have their own size.
+// Hovering over a cell highlights it.
+
+const COLUMN_WIDTHS: [f32; 6] = [40., 120., 80., 80., 200., 60.];
+const ROW_HEIGHTS: [f32; 8] = [32., 20., 20., 48., 20., 20., 64., 20.];
+
+fn spawn_sheet(mut commands: Commands) {
+    let cell_sizes = TilemapCellSizes::new(COLUMN_WIDTHS, ROW_HEIGHTS);
+    let tilemap = Tilemap::from_fn(COLUMN_WIDTHS.len(), ROW_HEIGHTS.len(), |x, y| SpriteTile {
+        custom_size: Some(cell_sizes.cell_size([x, y])),
+        ..Default::default()
+    });
+    commands.spawn((
+        SpriteTilemapBundle {
+            tilemap,
+            geometry: TilemapGeometry {
+                spacing: 2. * Vec2::ONE,
+                margin: 4. * Vec2::ONE,
+                reverse_columns: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        },
+        cell_sizes,
+    ));
+}
+
+fn highlight(
+    windows: Res<Windows>,
+    camera_query: Query<(&Camera, &GlobalTransform), With<Camera2d>>,
+    mut tilemap_query: Query<(
+        &mut Tilemap<SpriteTile>,
+        &TilemapGeometry,
+        &TilemapCellSizes,
+        &GlobalTransform,
+    )>,
+) {
+    let (camera, camera_transform) = camera_query.single();
+    let window = match camera.target {
+        RenderTarget::Window(id) => windows.get(id),
+        _ => windows.get_primary(),
+    };
+    let world_point = window.and_then(|window| {
+        let cursor = window.cursor_position()?;
+        let window_size = Vec2::new(window.width(), window.height());
+        let ndc = (cursor / window_size) * 2.0 - Vec2::ONE;
+        let ndc_to_world = camera_transform.compute_matrix() * camera.projection_matrix().inverse();
+        Some(ndc_to_world.project_point3(ndc.extend(-1.0)).truncate())
+    });
+    for (mut tilemap, geometry, cell_sizes, transform) in tilemap_query.iter_mut() {
+        let picked = world_point.and_then(|point| cell_sizes.pick(point, transform, geometry));
+        for (x, y, tile) in tilemap.indexed_iter_mut() {
+            tile.color = if picked == Some([x, y]) {
+                Color::ORANGE
+            } else if y == 0 || x == 0 {
+                Color::GRAY
+            } else {
+                Color::WHITE
+            };
+        }
+    }
+}
+
+fn main() {
+    App::new()
+        .add_plugins(DefaultPlugins)
+        .add_plugin(SpriteTilemapPlugin)
+        .add_startup_system(|mut commands: Commands| {
+            commands.spawn(Camera2dBundle::default());
+        })
+        .add_startup_system(spawn_sheet)
+        .add_system(highlight)
+        .run();
+}
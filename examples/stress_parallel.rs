@@ -0,0 +1,72 @@
+use bevy::diagnostic::{FrameTimeDiagnosticsPlugin, LogDiagnosticsPlugin};
+use bevy::prelude::*;
+use bevy_sprite_tilemap::prelude::*;
+
+// Press space to switch between serial and parallel extraction
+
+fn spawn_grids(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut texture_atlases: ResMut<Assets<TextureAtlas>>,
+) {
+    let tile_size = 16.0 * Vec2::ONE;
+    let texture_atlas_image = asset_server.load("test_tileset.png");
+    let texture_atlas = TextureAtlas::from_grid(texture_atlas_image, tile_size, 4, 4, None, None);
+    let texture_atlas = texture_atlases.add(texture_atlas);
+    for i in 0..4 {
+        let tilemap = Tilemap::from_fn(250, 250, |x, y| TextureAtlasTile::new((x + y + i) % 16));
+        commands.spawn(TextureAtlasTilemapBundle {
+            tilemap,
+            geometry: TilemapGeometry {
+                tile_size,
+                ..Default::default()
+            },
+            texture_atlas: texture_atlas.clone(),
+            transform: Transform::from_xyz(0., 0., i as f32).with_scale(0.25 * Vec3::ONE),
+            ..Default::default()
+        });
+    }
+}
+
+fn toggle_parallel(
+    keyboard_input: Res<Input<KeyCode>>,
+    mut settings: ResMut<TilemapExtractionSettings>,
+) {
+    if keyboard_input.just_pressed(KeyCode::Space) {
+        settings.parallel_threshold = if settings.parallel_threshold == usize::MAX {
+            TilemapExtractionSettings::default().parallel_threshold
+        } else {
+            usize::MAX
+        };
+        info!(
+            "{} extraction",
+            if settings.parallel_threshold == usize::MAX {
+                "serial"
+            } else {
+                "parallel"
+            }
+        );
+    }
+}
+
+fn main() {
+    App::new()
+        .add_plugins(DefaultPlugins.set(WindowPlugin {
+            window: WindowDescriptor {
+                width: 1000.,
+                height: 1000.,
+                present_mode: bevy::window::PresentMode::Immediate,
+                ..Default::default()
+            },
+            ..Default::default()
+        }))
+        .add_plugin(LogDiagnosticsPlugin::default())
+        .add_plugin(FrameTimeDiagnosticsPlugin::default())
+        .add_plugin(SpriteTilemapPlugin)
+        .add_startup_system(|mut commands: Commands| {
+            commands.spawn(Camera2dBundle::default());
+        })
+        .add_startup_system(spawn_grids)
+        .add_system(toggle_parallel)
+        .run();
+}
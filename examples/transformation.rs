@@ -32,6 +32,7 @@ fn spawn_grids(
                     reverse_rows,
                     reverse_columns,
                     anchor,
+                    ..Default::default()
                 };
 
                 commands.spawn(TextureAtlasTilemapBundle {
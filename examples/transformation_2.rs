@@ -36,6 +36,7 @@ fn spawn_grids(
                     reverse_rows,
                     reverse_columns,
                     anchor,
+                    ..Default::default()
                 };
                 for sprite in &mut atlas_grid {
                     sprite.color = color;
@@ -0,0 +1,62 @@
+use bevy::prelude::*;
+use bevy_sprite_tilemap::prelude::*;
+
+// A minimap drawn inside a UI panel
+
+fn spawn_ui(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut texture_atlases: ResMut<Assets<TextureAtlas>>,
+) {
+    let tile_size = 16.0 * Vec2::ONE;
+    let texture_atlas_image = asset_server.load("test_tileset.png");
+    let texture_atlas = TextureAtlas::from_grid(texture_atlas_image, tile_size, 4, 4, None, None);
+    let tilemap = Tilemap::from_fn(16, 12, |x, y| TextureAtlasTile::new((x + 3 * y) % 16));
+
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.), Val::Percent(100.)),
+                justify_content: JustifyContent::FlexEnd,
+                align_items: AlignItems::FlexEnd,
+                ..Default::default()
+            },
+            background_color: Color::NONE.into(),
+            ..Default::default()
+        })
+        .with_children(|parent| {
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        size: Size::new(Val::Px(320.), Val::Px(240.)),
+                        padding: UiRect::all(Val::Px(8.)),
+                        margin: UiRect::all(Val::Px(16.)),
+                        ..Default::default()
+                    },
+                    background_color: Color::DARK_GRAY.into(),
+                    ..Default::default()
+                })
+                .with_children(|parent| {
+                    parent.spawn(UiTilemapBundle {
+                        style: Style {
+                            size: Size::new(Val::Percent(100.), Val::Percent(100.)),
+                            ..Default::default()
+                        },
+                        tilemap,
+                        texture_atlas: texture_atlases.add(texture_atlas),
+                        ..Default::default()
+                    });
+                });
+        });
+}
+
+fn main() {
+    App::new()
+        .add_plugins(DefaultPlugins)
+        .add_plugin(SpriteTilemapPlugin)
+        .add_startup_system(|mut commands: Commands| {
+            commands.spawn(Camera2dBundle::default());
+        })
+        .add_startup_system(spawn_ui)
+        .run();
+}
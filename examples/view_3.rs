@@ -28,6 +28,7 @@ fn spawn_grids(
             reverse_rows,
             reverse_columns,
             anchor,
+            ..Default::default()
         };
         commands.spawn(TextureAtlasTilemapBundle {
             tilemap: atlas_grid_dark.clone(),
@@ -0,0 +1,62 @@
+use bevy::prelude::*;
+use bevy_sprite_tilemap::prelude::*;
+
+fn spawn_grid(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut texture_atlases: ResMut<Assets<TextureAtlas>>,
+) {
+    let tile_size = 16.0 * Vec2::ONE;
+    let tilemap = Tilemap::from_fn(4, 4, |x, y| TextureAtlasTile::new(x + y * 4));
+    let texture_atlas_image = asset_server.load("test_tileset.png");
+    let texture_atlas = TextureAtlas::from_grid(texture_atlas_image, tile_size, 4, 4, None, None);
+
+    commands.spawn(TextureAtlasTilemapBundle {
+        tilemap,
+        geometry: TilemapGeometry {
+            tile_size,
+            ..Default::default()
+        },
+        view: TilemapView::Wrapping {
+            x: 0,
+            y: 0,
+            width: 12,
+            height: 8,
+        },
+        texture_atlas: texture_atlases.add(texture_atlas),
+        ..Default::default()
+    });
+}
+
+fn scroll(
+    time: Res<Time>,
+    mut timer: Local<f32>,
+    mut tilemap_query: Query<&mut TilemapView>,
+    mut camera_query: Query<&mut Transform, With<Camera>>,
+) {
+    *timer += time.delta_seconds();
+    if *timer < 0.25 {
+        return;
+    }
+    *timer = 0.;
+    for mut view in tilemap_query.iter_mut() {
+        if let TilemapView::Wrapping { x, .. } = &mut *view {
+            *x += 1;
+        }
+    }
+    for mut camera_transform in camera_query.iter_mut() {
+        camera_transform.translation.x += 16.;
+    }
+}
+
+fn main() {
+    App::new()
+        .add_plugins(DefaultPlugins)
+        .add_plugin(SpriteTilemapPlugin)
+        .add_startup_system(|mut commands: Commands| {
+            commands.spawn(Camera2dBundle::default());
+        })
+        .add_startup_system(spawn_grid)
+        .add_system(scroll)
+        .run();
+}
@@ -0,0 +1,179 @@
+use crate::indexing::IndexableGrid;
+use crate::orientation::Orientation;
+use crate::tile::Tileable;
+use crate::tilemap::Tilemap;
+use bevy::prelude::*;
+
+#[inline]
+pub(crate) fn clip_region(map_size: [usize; 2], [x, y, width, height]: [usize; 4]) -> [usize; 4] {
+    [
+        x,
+        y,
+        width.min(map_size[0].saturating_sub(x)),
+        height.min(map_size[1].saturating_sub(y)),
+    ]
+}
+
+/// Returns the first target cell, the first cell within the pasted region and the length
+/// of the overlap of a region of `length` cells pasted at `position` along one axis
+#[inline]
+pub(crate) fn clip_paste_axis(space: usize, position: i32, length: usize) -> (usize, usize, usize) {
+    let start = (position as i64).max(0);
+    let end = (position as i64 + length as i64).min(space as i64);
+    if end <= start {
+        return (0, 0, 0);
+    }
+    (
+        start as usize,
+        (start - position as i64) as usize,
+        (end - start) as usize,
+    )
+}
+
+impl<T> Tilemap<T>
+where
+    T: Tileable,
+{
+    /// Copy the cells of a region `[x, y, width, height]` into a new tilemap
+    pub fn copy_region(&self, region: [usize; 4]) -> Tilemap<T> {
+        let [x, y, width, height] = clip_region([self.width(), self.height()], region);
+        Tilemap::from_fn(width, height, |i, j| self[[x + i, y + j]].clone())
+    }
+
+    /// Copy a region `[x, y, width, height]` of `source` into this tilemap,
+    /// reoriented and with its bottom left cell placed at `destination`.
+    /// Cells falling outside of either tilemap are skipped.
+    pub fn blit(
+        &mut self,
+        source: &Tilemap<T>,
+        region: [usize; 4],
+        destination: IVec2,
+        orientation: Orientation,
+    ) {
+        if orientation == Orientation::Identity {
+            let [x, y, width, height] = clip_region([source.width(), source.height()], region);
+            let (target_x, skip_x, width) = clip_paste_axis(self.width(), destination.x, width);
+            let (target_y, skip_y, height) = clip_paste_axis(self.height(), destination.y, height);
+            let source_x = x + skip_x;
+            for row in 0..height {
+                let source_row = &source.row(y + skip_y + row)[source_x..source_x + width];
+                self.row_mut(target_y + row)[target_x..target_x + width]
+                    .clone_from_slice(source_row);
+            }
+        } else {
+            self.blit_with(source, region, destination, orientation, |target, tile| {
+                *target = tile.clone()
+            });
+        }
+    }
+
+    /// Blit a region `[x, y, width, height]` of `source` into this tilemap,
+    /// combining each source tile with the tile it lands on using `merge`.
+    pub fn blit_with<S>(
+        &mut self,
+        source: &Tilemap<S>,
+        region: [usize; 4],
+        destination: IVec2,
+        orientation: Orientation,
+        mut merge: impl FnMut(&mut T, &S),
+    ) where
+        S: Tileable,
+    {
+        let [x, y, width, height] = clip_region([source.width(), source.height()], region);
+        let size = orientation.size([width, height]);
+        let inverse = orientation.inverse();
+        let (target_x, skip_x, columns) = clip_paste_axis(self.width(), destination.x, size[0]);
+        let (target_y, skip_y, rows) = clip_paste_axis(self.height(), destination.y, size[1]);
+        for j in 0..rows {
+            for i in 0..columns {
+                let [u, v] = inverse.apply([skip_x + i, skip_y + j], size);
+                let index = self.index_grid(target_x + i, target_y + j);
+                merge(&mut self[index], &source[[x + u, y + v]]);
+            }
+        }
+    }
+}
+
+impl<U> Tilemap<Option<U>>
+where
+    U: Tileable,
+{
+    /// Blit a region `[x, y, width, height]` of `source` into this tilemap,
+    /// leaving cells under empty source cells unchanged.
+    pub fn blit_skip_none(
+        &mut self,
+        source: &Tilemap<Option<U>>,
+        region: [usize; 4],
+        destination: IVec2,
+        orientation: Orientation,
+    ) {
+        self.blit_with(source, region, destination, orientation, |target, tile| {
+            if tile.is_some() {
+                *target = tile.clone();
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::math::ivec2;
+
+    #[derive(Clone, Debug, Default, PartialEq)]
+    struct Cell(usize);
+
+    impl Tileable for Cell {}
+
+    fn numbered(width: usize, height: usize) -> Tilemap<Cell> {
+        Tilemap::from_fn(width, height, |x, y| Cell(1 + x + 10 * y))
+    }
+
+    #[test]
+    fn blit_clips() {
+        let source = numbered(4, 3);
+        let mut target = Tilemap::<Cell>::from_default(5, 5);
+        target.blit(&source, [1, 1, 10, 10], ivec2(-1, 3), Orientation::Identity);
+        assert_eq!(target[[0, 3]], Cell(13));
+        assert_eq!(target[[1, 3]], Cell(14));
+        assert_eq!(target[[0, 4]], Cell(23));
+        assert_eq!(target.into_iter().filter(|cell| cell.0 != 0).count(), 4);
+    }
+
+    #[test]
+    fn blit_orientations_match_apply() {
+        let source = numbered(3, 2);
+        for orientation in Orientation::ALL {
+            let mut target = Tilemap::<Cell>::from_default(6, 6);
+            target.blit(&source, [0, 0, 3, 2], ivec2(1, 2), orientation);
+            for (x, y, cell) in source.indexed_iter() {
+                let [u, v] = orientation.apply([x, y], [3, 2]);
+                assert_eq!(&target[[1 + u, 2 + v]], cell, "{orientation:?}");
+            }
+        }
+
+        let mut target = Tilemap::<Cell>::from_default(2, 3);
+        target.blit(&source, [0, 0, 3, 2], IVec2::ZERO, Orientation::Rotate90);
+        assert_eq!(target[[1, 0]], Cell(1));
+        assert_eq!(target[[0, 0]], Cell(11));
+        assert_eq!(target[[1, 2]], Cell(3));
+    }
+
+    #[test]
+    fn skip_none() {
+        let source = Tilemap::from_fn(2, 2, |x, y| (x == y).then_some(Cell(1)));
+        let mut target = Tilemap::from_elem(2, 2, Some(Cell(2)));
+        target.blit_skip_none(&source, [0, 0, 2, 2], IVec2::ZERO, Orientation::Identity);
+        assert_eq!(target[[0, 0]], Some(Cell(1)));
+        assert_eq!(target[[1, 0]], Some(Cell(2)));
+    }
+
+    #[test]
+    fn copy_region() {
+        let source = numbered(4, 3);
+        let copy = source.copy_region([2, 1, 5, 5]);
+        assert_eq!([copy.width(), copy.height()], [2, 2]);
+        assert_eq!(copy[[0, 0]], Cell(13));
+        assert_eq!(copy[[1, 1]], Cell(24));
+    }
+}
@@ -0,0 +1,404 @@
+use crate::geometry::TilemapGeometry;
+use bevy::math::vec2;
+use bevy::prelude::*;
+use std::ops::Range;
+
+/// Sizes of the cells along one axis, stored as prefix sums
+#[derive(Clone, Debug)]
+struct SizedAxis {
+    /// `starts[i]` is the total size of the cells before cell `i`, without spacing
+    starts: Vec<f32>,
+    /// size of the largest cell
+    largest: f32,
+}
+
+/// Spacing, margin, anchor and direction of one axis of a [`TilemapGeometry`]
+#[derive(Clone, Copy)]
+struct AxisLayout {
+    spacing: f32,
+    margin: f32,
+    anchor: f32,
+    reverse: bool,
+}
+
+impl Default for SizedAxis {
+    fn default() -> Self {
+        Self::new([])
+    }
+}
+
+impl SizedAxis {
+    fn new(sizes: impl IntoIterator<Item = f32>) -> Self {
+        let mut starts = vec![0.];
+        let mut total = 0.;
+        let mut largest: f32 = 0.;
+        for size in sizes {
+            let size = size.max(0.);
+            total += size;
+            largest = largest.max(size);
+            starts.push(total);
+        }
+        Self { starts, largest }
+    }
+
+    #[inline]
+    fn len(&self) -> usize {
+        self.starts.len() - 1
+    }
+
+    #[inline]
+    fn size(&self, i: usize) -> f32 {
+        self.starts[i + 1] - self.starts[i]
+    }
+
+    /// Distance from the inner edge of the margin to the start of cell `i`
+    #[inline]
+    fn start(&self, i: usize, spacing: f32) -> f32 {
+        self.starts[i] + i as f32 * spacing
+    }
+
+    fn extent(&self, layout: AxisLayout) -> f32 {
+        let n = self.len();
+        self.starts[n] + n.saturating_sub(1) as f32 * layout.spacing + 2. * layout.margin
+    }
+
+    /// Local position of the bottom or left edge of the grid including its margin
+    #[inline]
+    fn base(&self, layout: AxisLayout) -> f32 {
+        -(0.5 + layout.anchor) * self.extent(layout)
+    }
+
+    /// Local position of the centre of cell `i`
+    fn centre(&self, layout: AxisLayout, i: usize) -> f32 {
+        let offset = layout.margin + self.start(i, layout.spacing) + 0.5 * self.size(i);
+        if layout.reverse {
+            self.base(layout) + self.extent(layout) - offset
+        } else {
+            self.base(layout) + offset
+        }
+    }
+
+    /// Distance from the inner edge of the margin to the local position `point`
+    #[inline]
+    fn inner_offset(&self, layout: AxisLayout, point: f32) -> f32 {
+        let from_base = point - self.base(layout);
+        let from_base = if layout.reverse {
+            self.extent(layout) - from_base
+        } else {
+            from_base
+        };
+        from_base - layout.margin
+    }
+
+    /// Index of the last cell starting at or before `offset`, found by binary search
+    fn search(&self, spacing: f32, offset: f32) -> Option<usize> {
+        let (mut low, mut high) = (0, self.len());
+        while low < high {
+            let middle = (low + high) / 2;
+            if self.start(middle, spacing) <= offset {
+                low = middle + 1;
+            } else {
+                high = middle;
+            }
+        }
+        low.checked_sub(1)
+    }
+
+    /// The cell covering the local position `point`, if any
+    fn cell_at(&self, layout: AxisLayout, point: f32) -> Option<usize> {
+        let offset = self.inner_offset(layout, point);
+        let i = self.search(layout.spacing, offset)?;
+        (offset < self.start(i, layout.spacing) + self.size(i)).then_some(i)
+    }
+
+    /// The cells overlapping the local range `min..=max`
+    fn range(&self, layout: AxisLayout, min: f32, max: f32) -> Range<usize> {
+        let (a, b) = (
+            self.inner_offset(layout, min),
+            self.inner_offset(layout, max),
+        );
+        let (low, high) = (a.min(b), a.max(b));
+        let first = match self.search(layout.spacing, low) {
+            Some(i) if self.start(i, layout.spacing) + self.size(i) <= low => i + 1,
+            Some(i) => i,
+            None => 0,
+        };
+        let end = self
+            .search(layout.spacing, high)
+            .map_or(0, |last| last + 1)
+            .max(first);
+        first..end
+    }
+}
+
+/// Gives each column and row of a tilemap its own size, replacing the geometry's `tile_size`.
+///
+/// The geometry's spacing, margin, anchor and reversal are still applied.
+/// Views select which cells are drawn, but every cell is drawn in its own position.
+/// Cells beyond the given columns and rows are not drawn.
+/// Cells outside of the cameras' views are culled, except for views that wrap around the tilemap.
+#[derive(Clone, Component, Debug, Default)]
+pub struct TilemapCellSizes {
+    columns: SizedAxis,
+    rows: SizedAxis,
+}
+
+impl TilemapCellSizes {
+    pub fn new(
+        column_widths: impl IntoIterator<Item = f32>,
+        row_heights: impl IntoIterator<Item = f32>,
+    ) -> Self {
+        Self {
+            columns: SizedAxis::new(column_widths),
+            rows: SizedAxis::new(row_heights),
+        }
+    }
+
+    /// Number of columns and rows with sizes
+    #[inline]
+    pub fn dimensions(&self) -> [usize; 2] {
+        [self.columns.len(), self.rows.len()]
+    }
+
+    #[inline]
+    fn layouts(geometry: &TilemapGeometry) -> (AxisLayout, AxisLayout) {
+        let anchor = geometry.anchor.as_vec();
+        (
+            AxisLayout {
+                spacing: geometry.spacing.x,
+                margin: geometry.margin.x,
+                anchor: anchor.x,
+                reverse: geometry.reverse_rows,
+            },
+            AxisLayout {
+                spacing: geometry.spacing.y,
+                margin: geometry.margin.y,
+                anchor: anchor.y,
+                reverse: geometry.reverse_columns,
+            },
+        )
+    }
+
+    /// Size of the cell `[x, y]`
+    #[inline]
+    pub fn cell_size(&self, [x, y]: [usize; 2]) -> Vec2 {
+        vec2(self.columns.size(x), self.rows.size(y))
+    }
+
+    /// Width of the widest column and height of the tallest row
+    #[inline]
+    pub fn largest_cell_size(&self) -> Vec2 {
+        vec2(self.columns.largest, self.rows.largest)
+    }
+
+    /// Size of the grid including its margin
+    pub fn grid_extent(&self, geometry: &TilemapGeometry) -> Vec2 {
+        let (columns, rows) = Self::layouts(geometry);
+        vec2(self.columns.extent(columns), self.rows.extent(rows))
+    }
+
+    /// Position of the centre of cell `[x, y]` relative to the transform
+    pub fn cell_to_local(&self, geometry: &TilemapGeometry, [x, y]: [usize; 2]) -> Vec2 {
+        let (columns, rows) = Self::layouts(geometry);
+        vec2(self.columns.centre(columns, x), self.rows.centre(rows, y))
+    }
+
+    /// Position relative to the transform of the point `point` in cell coordinates,
+    /// with cell centres at whole numbered coordinates.
+    /// Points are offset from the centre of their nearest cell in proportion to its size.
+    /// Returns `None` if there are no cells.
+    pub fn point_to_local(&self, geometry: &TilemapGeometry, point: Vec2) -> Option<Vec2> {
+        let [columns, rows] = self.dimensions();
+        if columns == 0 || rows == 0 {
+            return None;
+        }
+        let x = (point.x.round().max(0.) as usize).min(columns - 1);
+        let y = (point.y.round().max(0.) as usize).min(rows - 1);
+        let direction = vec2(
+            if geometry.reverse_rows { -1. } else { 1. },
+            if geometry.reverse_columns { -1. } else { 1. },
+        );
+        let offset = point - vec2(x as f32, y as f32);
+        Some(self.cell_to_local(geometry, [x, y]) + offset * self.cell_size([x, y]) * direction)
+    }
+
+    /// The cell covering a position relative to the transform,
+    /// or `None` if the position is outside of the grid or in the spacing between cells
+    pub fn local_to_cell(&self, geometry: &TilemapGeometry, point: Vec2) -> Option<[usize; 2]> {
+        let (columns, rows) = Self::layouts(geometry);
+        Some([
+            self.columns.cell_at(columns, point.x)?,
+            self.rows.cell_at(rows, point.y)?,
+        ])
+    }
+
+    /// The columns and rows of cells overlapping the rectangle from `min` to `max`
+    /// relative to the transform
+    pub fn cell_range(
+        &self,
+        geometry: &TilemapGeometry,
+        min: Vec2,
+        max: Vec2,
+    ) -> [Range<usize>; 2] {
+        let (columns, rows) = Self::layouts(geometry);
+        [
+            self.columns.range(columns, min.x, max.x),
+            self.rows.range(rows, min.y, max.y),
+        ]
+    }
+
+    /// Bounds of the grid including its margin, relative to the transform
+    pub fn bounds(&self, geometry: &TilemapGeometry) -> Rect {
+        let (columns, rows) = Self::layouts(geometry);
+        let min = vec2(self.columns.base(columns), self.rows.base(rows));
+        Rect {
+            min,
+            max: min + self.grid_extent(geometry),
+        }
+    }
+
+    /// Find the cell drawn under a point in world space
+    pub fn pick(
+        &self,
+        world_point: Vec2,
+        transform: &GlobalTransform,
+        geometry: &TilemapGeometry,
+    ) -> Option<[usize; 2]> {
+        let local = transform
+            .affine()
+            .inverse()
+            .transform_point3(world_point.extend(0.))
+            .truncate();
+        self.local_to_cell(geometry, local)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::sprite::Anchor;
+
+    fn sizes() -> TilemapCellSizes {
+        TilemapCellSizes::new([10., 30., 20.], [5., 15.])
+    }
+
+    #[test]
+    fn centres_and_bounds() {
+        let geometry = TilemapGeometry {
+            anchor: Anchor::BottomLeft,
+            spacing: vec2(2., 0.),
+            margin: vec2(1., 1.),
+            ..Default::default()
+        };
+        let sizes = sizes();
+        assert_eq!(sizes.grid_extent(&geometry), vec2(66., 22.));
+        assert_eq!(sizes.cell_to_local(&geometry, [0, 0]), vec2(6., 3.5));
+        assert_eq!(sizes.cell_to_local(&geometry, [1, 1]), vec2(28., 13.5));
+        assert_eq!(sizes.cell_to_local(&geometry, [2, 0]), vec2(55., 3.5));
+        assert_eq!(
+            sizes.bounds(&geometry),
+            Rect::from_corners(Vec2::ZERO, vec2(66., 22.))
+        );
+
+        let reversed = TilemapGeometry {
+            reverse_rows: true,
+            ..geometry
+        };
+        assert_eq!(sizes.cell_to_local(&reversed, [2, 0]), vec2(11., 3.5));
+    }
+
+    #[test]
+    fn picking_by_binary_search() {
+        let geometry = TilemapGeometry {
+            anchor: Anchor::BottomLeft,
+            spacing: vec2(2., 0.),
+            margin: vec2(1., 1.),
+            ..Default::default()
+        };
+        let sizes = sizes();
+        let cell = |x: f32, y: f32| sizes.local_to_cell(&geometry, vec2(x, y));
+        assert_eq!(cell(1.5, 1.5), Some([0, 0]));
+        assert_eq!(cell(11.5, 1.5), None);
+        assert_eq!(cell(13.5, 10.), Some([1, 1]));
+        assert_eq!(cell(64.5, 20.5), Some([2, 1]));
+        assert_eq!(cell(65.5, 10.), None);
+        assert_eq!(cell(0.5, 10.), None);
+
+        for x in 0..3 {
+            for y in 0..2 {
+                let centre = sizes.cell_to_local(&geometry, [x, y]);
+                assert_eq!(sizes.local_to_cell(&geometry, centre), Some([x, y]));
+            }
+        }
+
+        assert_eq!(
+            sizes.cell_range(&geometry, vec2(12., 0.), vec2(40., 4.)),
+            [1..2, 0..1]
+        );
+        assert_eq!(
+            sizes.cell_range(&geometry, vec2(-50., -50.), vec2(100., 100.)),
+            [0..3, 0..2]
+        );
+        assert!(sizes.cell_range(&geometry, vec2(100., 0.), vec2(200., 4.))[0].is_empty());
+    }
+
+    #[test]
+    fn points_between_cells() {
+        let geometry = TilemapGeometry {
+            anchor: Anchor::BottomLeft,
+            ..Default::default()
+        };
+        let sizes = sizes();
+        assert_eq!(
+            sizes.point_to_local(&geometry, vec2(1., 0.)),
+            Some(sizes.cell_to_local(&geometry, [1, 0]))
+        );
+        assert_eq!(
+            sizes.point_to_local(&geometry, vec2(0.5, 0.)),
+            Some(vec2(10., 2.5))
+        );
+        assert_eq!(
+            sizes.point_to_local(&geometry, vec2(0.25, 1.)),
+            Some(vec2(7.5, 12.5))
+        );
+        assert_eq!(
+            TilemapCellSizes::default().point_to_local(&geometry, Vec2::ZERO),
+            None
+        );
+    }
+
+    #[test]
+    fn views_select_picked_cells() {
+        use crate::geometry::TilemapView;
+        use crate::util::pick_tile;
+        let geometry = TilemapGeometry {
+            anchor: Anchor::BottomLeft,
+            ..Default::default()
+        };
+        let sizes = sizes();
+        let transform = GlobalTransform::IDENTITY;
+        let pick = |point: Vec2, view: TilemapView| {
+            pick_tile(point, &transform, 3, 2, &geometry, &view, Some(&sizes))
+        };
+        assert_eq!(pick(vec2(25., 10.), TilemapView::All), Some([1, 1]));
+        assert_eq!(pick(vec2(65., 10.), TilemapView::All), None);
+
+        let section = TilemapView::Section {
+            x: 2,
+            y: 0,
+            width: 1,
+            height: 2,
+        };
+        assert_eq!(pick(vec2(25., 10.), section.clone()), None);
+        assert_eq!(pick(vec2(50., 2.), section), Some([2, 0]));
+
+        let wrapping = TilemapView::Wrapping {
+            x: 2,
+            y: 0,
+            width: 2,
+            height: 2,
+        };
+        assert_eq!(pick(vec2(5., 2.), wrapping.clone()), Some([0, 0]));
+        assert_eq!(pick(vec2(25., 10.), wrapping), None);
+    }
+}
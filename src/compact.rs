@@ -0,0 +1,329 @@
+use crate::cell_sizes::TilemapCellSizes;
+use crate::extraction::calculate_tilemap_bounds;
+use crate::extraction::camera_areas;
+use crate::extraction::run_extraction_jobs;
+use crate::extraction::visible_window;
+use crate::extraction::ExtractionJob;
+use crate::extraction::TilemapExtractionSettings;
+use crate::extraction::TilemapRenderSystem;
+use crate::geometry::*;
+use crate::indexing::IndexableGrid;
+use crate::orientation::Orientation;
+use crate::tile::Tileable;
+use crate::tilemap::Tilemap;
+use bevy::prelude::*;
+use bevy::render::view::VisibilitySystems;
+use bevy::render::Extract;
+use bevy::render::RenderApp;
+use bevy::render::RenderStage;
+use bevy::sprite::ExtractedSprite;
+use bevy::sprite::ExtractedSprites;
+use bevy::sprite::SpriteSystem;
+use std::f32::consts::FRAC_PI_2;
+use std::marker::PhantomData;
+
+const ORIENTATION_MASK: u8 = 0b111;
+
+/// A four byte tile drawn from a texture atlas, for tilemaps with millions of cells.
+///
+/// Tiles are drawn centred in their cells at the size of their atlas image.
+/// Their color tint is looked up by index in the tilemap's [`TilePalette`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CompactTile {
+    /// index of the image in the texture atlas
+    pub index: u16,
+    /// index of the tile's color tint in the tilemap's [`TilePalette`]
+    pub tint: u8,
+    /// orientation of the image
+    flags: u8,
+}
+
+impl Tileable for CompactTile {}
+
+impl CompactTile {
+    #[inline]
+    pub fn new(index: u16) -> Self {
+        Self {
+            index,
+            ..Default::default()
+        }
+    }
+
+    #[inline]
+    pub fn with_tint(mut self, tint: u8) -> Self {
+        self.tint = tint;
+        self
+    }
+
+    #[inline]
+    pub fn with_orientation(mut self, orientation: Orientation) -> Self {
+        self.set_orientation(orientation);
+        self
+    }
+
+    /// How the image is rotated and reflected
+    #[inline]
+    pub fn orientation(&self) -> Orientation {
+        Orientation::ALL[(self.flags & ORIENTATION_MASK) as usize]
+    }
+
+    #[inline]
+    pub fn set_orientation(&mut self, orientation: Orientation) {
+        let bits = Orientation::ALL
+            .iter()
+            .position(|&other| other == orientation)
+            .unwrap_or(0) as u8;
+        self.flags = (self.flags & !ORIENTATION_MASK) | bits;
+    }
+
+    /// Sprite drawn for this tile in the cell with transform `transform`
+    pub fn extract(
+        &self,
+        entity: Entity,
+        transform: GlobalTransform,
+        texture_atlas: &TextureAtlas,
+        palette: Option<&TilePalette>,
+    ) -> Option<ExtractedSprite> {
+        let rect = *texture_atlas.textures.get(self.index as usize)?;
+        let (flip_x, flip_y, quarter_turns) = sprite_orientation(self.orientation());
+        let transform = if quarter_turns == 0 {
+            transform
+        } else {
+            transform.mul_transform(Transform::from_rotation(Quat::from_rotation_z(
+                quarter_turns as f32 * FRAC_PI_2,
+            )))
+        };
+        Some(ExtractedSprite {
+            entity,
+            transform,
+            color: palette.map_or(Color::WHITE, |palette| palette.color(self.tint)),
+            rect: Some(rect),
+            custom_size: None,
+            image_handle_id: texture_atlas.texture.id(),
+            flip_x,
+            flip_y,
+            anchor: Vec2::ZERO,
+        })
+    }
+}
+
+/// The sprite flips, applied first, and the number of anticlockwise quarter turns
+/// that draw an image reoriented by `orientation`
+#[inline]
+fn sprite_orientation(orientation: Orientation) -> (bool, bool, i32) {
+    match orientation {
+        Orientation::Identity => (false, false, 0),
+        Orientation::Rotate90 => (false, false, 1),
+        Orientation::Rotate180 => (true, true, 0),
+        Orientation::Rotate270 => (false, false, -1),
+        Orientation::FlipX => (true, false, 0),
+        Orientation::FlipY => (false, true, 0),
+        Orientation::Transpose => (false, true, 1),
+        Orientation::AntiTranspose => (true, false, 1),
+    }
+}
+
+/// Color tints shared by the [`CompactTile`]s of a tilemap.
+/// Tiles with a tint outside of the palette are drawn untinted.
+#[derive(Clone, Component, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TilePalette {
+    pub colors: Vec<Color>,
+}
+
+impl TilePalette {
+    pub fn new(colors: Vec<Color>) -> Self {
+        Self { colors }
+    }
+
+    #[inline]
+    pub fn color(&self, tint: u8) -> Color {
+        self.colors
+            .get(tint as usize)
+            .copied()
+            .unwrap_or(Color::WHITE)
+    }
+}
+
+/// Conversion from a tile to the [`CompactTile`] drawn for it
+pub trait AsCompactTile: Tileable {
+    /// The tile to draw, or `None` to leave the cell empty
+    fn as_compact_tile(&self) -> Option<CompactTile>;
+}
+
+impl AsCompactTile for CompactTile {
+    #[inline]
+    fn as_compact_tile(&self) -> Option<CompactTile> {
+        Some(*self)
+    }
+}
+
+impl<T> AsCompactTile for Option<T>
+where
+    T: AsCompactTile,
+{
+    #[inline]
+    fn as_compact_tile(&self) -> Option<CompactTile> {
+        self.as_ref().and_then(AsCompactTile::as_compact_tile)
+    }
+}
+
+#[derive(Bundle, Default)]
+pub struct CompactTilemapBundle {
+    pub tilemap: Tilemap<CompactTile>,
+    pub geometry: TilemapGeometry,
+    pub view: TilemapView,
+    pub texture_atlas: Handle<TextureAtlas>,
+    pub palette: TilePalette,
+    pub transform: Transform,
+    pub global_transform: GlobalTransform,
+    pub visibility: Visibility,
+    pub computed_visibility: ComputedVisibility,
+}
+
+#[allow(clippy::type_complexity)]
+pub fn extract_compact_tilemap<T>(
+    mut extracted_sprites: ResMut<ExtractedSprites>,
+    settings: Extract<Res<TilemapExtractionSettings>>,
+    texture_atlases: Extract<Res<Assets<TextureAtlas>>>,
+    camera_query: Extract<Query<(&Camera, &GlobalTransform)>>,
+    tilemap_query: Extract<
+        Query<(
+            Entity,
+            &Tilemap<T>,
+            &TilemapGeometry,
+            &TilemapView,
+            Option<&TilemapCellSizes>,
+            &Handle<TextureAtlas>,
+            Option<&TilePalette>,
+            &GlobalTransform,
+            &ComputedVisibility,
+        )>,
+    >,
+) where
+    T: AsCompactTile,
+{
+    let areas = camera_areas(camera_query.iter());
+    let jobs: Vec<_> = tilemap_query
+        .iter()
+        .filter(|(.., visibility)| visibility.is_visible())
+        .filter_map(
+            |(
+                entity,
+                tilemap,
+                geometry,
+                view,
+                cell_sizes,
+                texture_atlas_handle,
+                palette,
+                transform,
+                _,
+            )| {
+                let texture_atlas = texture_atlases.get(texture_atlas_handle)?;
+                let map_size = [tilemap.width(), tilemap.height()];
+                Some(ExtractionJob {
+                    map_size,
+                    window: visible_window(map_size, geometry, view, cell_sizes, transform, &areas),
+                    geometry,
+                    cell_sizes,
+                    transform: *transform,
+                    images: None,
+                    extract_tile: move |index, transform| {
+                        tilemap[index].as_compact_tile()?.extract(
+                            entity,
+                            transform,
+                            texture_atlas,
+                            palette,
+                        )
+                    },
+                })
+            },
+        )
+        .collect();
+    run_extraction_jobs(&mut extracted_sprites.sprites, &jobs, &settings);
+}
+
+/// Adds the system extracting `Tilemap<T>`s of compact tiles
+pub struct ExtractCompactTilemapPlugin<T>(PhantomData<fn() -> T>);
+
+impl<T> Default for ExtractCompactTilemapPlugin<T> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<T> Plugin for ExtractCompactTilemapPlugin<T>
+where
+    T: AsCompactTile,
+{
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TilemapExtractionSettings>()
+            .add_system_to_stage(
+                CoreStage::PostUpdate,
+                calculate_tilemap_bounds::<Tilemap<T>>.label(VisibilitySystems::CalculateBounds),
+            );
+        if let Ok(render_app) = app.get_sub_app_mut(RenderApp) {
+            render_app.add_system_to_stage(
+                RenderStage::Extract,
+                extract_compact_tilemap::<T>
+                    .label(TilemapRenderSystem::ExtractTiles)
+                    .after(SpriteSystem::ExtractSprites),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::math::vec2;
+
+    #[test]
+    fn compact_tile_is_four_bytes() {
+        assert_eq!(std::mem::size_of::<CompactTile>(), 4);
+    }
+
+    #[test]
+    fn orientation_round_trip() {
+        for orientation in Orientation::ALL {
+            let tile = CompactTile::new(7)
+                .with_tint(3)
+                .with_orientation(orientation);
+            assert_eq!(tile.orientation(), orientation);
+            assert_eq!(tile.index, 7);
+            assert_eq!(tile.tint, 3);
+        }
+    }
+
+    #[test]
+    fn sprite_orientation_matches_grid_orientation() {
+        // where the corners of a 2x2 image end up when drawn
+        for orientation in Orientation::ALL {
+            let (flip_x, flip_y, quarter_turns) = sprite_orientation(orientation);
+            let rotation = Mat2::from_angle(quarter_turns as f32 * FRAC_PI_2);
+            for [x, y] in [[0, 0], [1, 0], [0, 1], [1, 1]] {
+                let mut corner = vec2(x as f32 - 0.5, y as f32 - 0.5);
+                if flip_x {
+                    corner.x = -corner.x;
+                }
+                if flip_y {
+                    corner.y = -corner.y;
+                }
+                let drawn = rotation * corner + 0.5;
+                let [u, v] = orientation.apply([x, y], [2, 2]);
+                assert!(
+                    drawn.distance(vec2(u as f32, v as f32)) < 1e-5,
+                    "{orientation:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn palette_falls_back_to_white() {
+        let palette = TilePalette::new(vec![Color::RED]);
+        assert_eq!(palette.color(0), Color::RED);
+        assert_eq!(palette.color(1), Color::WHITE);
+    }
+}
@@ -0,0 +1,543 @@
+use crate::cell_sizes::TilemapCellSizes;
+use crate::compact::CompactTile;
+use crate::extraction::TilemapRenderSystem;
+use crate::geometry::TilemapGeometry;
+use crate::geometry::TilemapView;
+use crate::indexing::IndexableGrid;
+use crate::tile::SpriteTile;
+use crate::tile::TextureAtlasTile;
+use crate::tilemap::Tilemap;
+use bevy::math::vec2;
+use bevy::prelude::*;
+use bevy::render::texture::DEFAULT_IMAGE_HANDLE;
+use bevy::render::Extract;
+use bevy::render::RenderApp;
+use bevy::render::RenderStage;
+use bevy::sprite::ExtractedSprite;
+use bevy::sprite::ExtractedSprites;
+use bevy::sprite::SpriteSystem;
+use bevy::utils::HashMap;
+use copyless::VecHelper;
+use std::marker::PhantomData;
+
+/// What the tilemap debug overlay draws
+#[derive(Resource, Clone, Debug)]
+pub struct TilemapDebugSettings {
+    /// draw the overlay
+    pub enabled: bool,
+    /// key that toggles `enabled`
+    pub toggle_key: Option<KeyCode>,
+    /// color of the lines along the edges of the drawn cells, `None` to hide them
+    pub grid: Option<Color>,
+    /// color of the outline of the whole tilemap including its margin, `None` to hide it
+    pub bounds: Option<Color>,
+    /// color of the outline of the cells drawn by a [`TilemapView`] other than `All`, `None` to hide it
+    pub view: Option<Color>,
+    /// color of the cross marking the origin of the tilemap's transform,
+    /// where its anchor point is placed, `None` to hide it
+    pub anchor: Option<Color>,
+    /// font of the `[x, y]` labels drawn in each cell, `None` to hide them
+    pub labels: Option<Handle<Font>>,
+    /// font size of the labels, in the tilemap's local units
+    pub label_size: f32,
+    pub label_color: Color,
+    /// labels are not drawn for tilemaps with more visible cells than this
+    pub max_labels: usize,
+    /// width of the lines in world units
+    pub line_width: f32,
+    /// distance in front of the tilemap the overlay is drawn at
+    pub depth: f32,
+}
+
+impl Default for TilemapDebugSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            toggle_key: Some(KeyCode::F3),
+            grid: Some(Color::rgba(1., 1., 1., 0.5)),
+            bounds: Some(Color::YELLOW),
+            view: Some(Color::CYAN),
+            anchor: Some(Color::RED),
+            labels: None,
+            label_size: 8.,
+            label_color: Color::WHITE,
+            max_labels: 4_096,
+            line_width: 1.,
+            depth: 1.,
+        }
+    }
+}
+
+/// The text entities labelling the cells of a tilemap, children of the tilemap
+#[derive(Component, Debug, Default)]
+pub struct TilemapDebugLabels {
+    pub labels: Vec<Entity>,
+}
+
+/// Cells drawn by `view` with their centres and sizes relative to the transform
+fn drawn_cells(
+    map_size: [usize; 2],
+    geometry: &TilemapGeometry,
+    view: &TilemapView,
+    cell_sizes: Option<&TilemapCellSizes>,
+) -> Vec<([usize; 2], Vec2, Vec2)> {
+    let window = view.window(map_size);
+    let mut cells = Vec::with_capacity(window.width * window.height);
+    for j in 0..window.height {
+        let y = (window.y + j) % map_size[1];
+        for i in 0..window.width {
+            let x = (window.x + i) % map_size[0];
+            match cell_sizes {
+                Some(cell_sizes) => {
+                    let [columns, rows] = cell_sizes.dimensions();
+                    if x < columns && y < rows {
+                        cells.push((
+                            [x, y],
+                            cell_sizes.cell_to_local(geometry, [x, y]),
+                            cell_sizes.cell_size([x, y]),
+                        ));
+                    }
+                }
+                None => cells.push((
+                    [x, y],
+                    geometry.cell_to_local(window.layout, window.origin + vec2(i as f32, j as f32)),
+                    geometry.tile_size.abs(),
+                )),
+            }
+        }
+    }
+    cells
+}
+
+/// Positions relative to the transform of the edges of the drawn columns along `axis` 0,
+/// or of the drawn rows along `axis` 1, in order and without duplicates
+fn grid_edges(
+    map_size: [usize; 2],
+    geometry: &TilemapGeometry,
+    view: &TilemapView,
+    cell_sizes: Option<&TilemapCellSizes>,
+    axis: usize,
+) -> Vec<f32> {
+    let window = view.window(map_size);
+    if window.width == 0 || window.height == 0 {
+        return vec![];
+    }
+    let (first, count) = match axis {
+        0 => (window.x, window.width),
+        _ => (window.y, window.height),
+    };
+    let mut edges = Vec::with_capacity(2 * count);
+    for i in 0..count {
+        let (centre, size) = match cell_sizes {
+            Some(cell_sizes) => {
+                let dimensions = cell_sizes.dimensions();
+                let index = (first + i) % map_size[axis];
+                if dimensions[0] == 0 || dimensions[1] == 0 || dimensions[axis] <= index {
+                    continue;
+                }
+                let mut cell = [0, 0];
+                cell[axis] = index;
+                (
+                    cell_sizes.cell_to_local(geometry, cell)[axis],
+                    cell_sizes.cell_size(cell)[axis],
+                )
+            }
+            None => {
+                let mut offset = Vec2::ZERO;
+                offset[axis] = i as f32;
+                (
+                    geometry.cell_to_local(window.layout, window.origin + offset)[axis],
+                    geometry.tile_size.abs()[axis],
+                )
+            }
+        };
+        edges.extend([centre - 0.5 * size, centre + 0.5 * size]);
+    }
+    edges.sort_by(f32::total_cmp);
+    edges.dedup_by(|a, b| (*a - *b).abs() < 1e-3);
+    edges
+}
+
+/// Outline of `rect` as four lines
+fn outline(rect: Rect, color: Color) -> [(Vec2, Vec2, Color); 4] {
+    let corners = [
+        rect.min,
+        vec2(rect.max.x, rect.min.y),
+        rect.max,
+        vec2(rect.min.x, rect.max.y),
+    ];
+    [0, 1, 2, 3].map(|i| (corners[i], corners[(i + 1) % 4], color))
+}
+
+/// Appends the lines of the debug overlay relative to the transform to `lines`,
+/// as their end points and color
+pub fn debug_lines(
+    map_size: [usize; 2],
+    geometry: &TilemapGeometry,
+    view: &TilemapView,
+    cell_sizes: Option<&TilemapCellSizes>,
+    settings: &TilemapDebugSettings,
+    lines: &mut Vec<(Vec2, Vec2, Color)>,
+) {
+    if let Some(color) = settings.grid {
+        let xs = grid_edges(map_size, geometry, view, cell_sizes, 0);
+        let ys = grid_edges(map_size, geometry, view, cell_sizes, 1);
+        if let (Some(&left), Some(&right), Some(&bottom), Some(&top)) =
+            (xs.first(), xs.last(), ys.first(), ys.last())
+        {
+            lines.extend(xs.iter().map(|&x| (vec2(x, bottom), vec2(x, top), color)));
+            lines.extend(ys.iter().map(|&y| (vec2(left, y), vec2(right, y), color)));
+        }
+    }
+    if let Some(color) = settings.bounds {
+        let bounds = match cell_sizes {
+            Some(cell_sizes) => Some(cell_sizes.bounds(geometry)),
+            None => geometry.view_bounds(map_size, &TilemapView::All),
+        };
+        if let Some(bounds) = bounds {
+            lines.extend(outline(bounds, color));
+        }
+    }
+    if let Some(color) = settings.view {
+        if cell_sizes.is_none() && !matches!(view, TilemapView::All) {
+            if let Some(bounds) = geometry.view_bounds(map_size, view) {
+                lines.extend(outline(bounds, color));
+            }
+        }
+    }
+    if let Some(color) = settings.anchor {
+        let arm = 0.25 * geometry.tile_size.abs().min_element();
+        lines.push((vec2(-arm, 0.), vec2(arm, 0.), color));
+        lines.push((vec2(0., -arm), vec2(0., arm), color));
+    }
+}
+
+/// A sprite drawing the line from `a` to `b` in world space
+fn line_sprite(entity: Entity, a: Vec3, b: Vec3, width: f32, color: Color) -> ExtractedSprite {
+    let direction = (b - a).truncate();
+    ExtractedSprite {
+        entity,
+        transform: Transform {
+            translation: 0.5 * (a + b),
+            rotation: Quat::from_rotation_z(direction.y.atan2(direction.x)),
+            scale: Vec3::ONE,
+        }
+        .into(),
+        color,
+        rect: None,
+        custom_size: Some(vec2(direction.length() + width, width)),
+        image_handle_id: DEFAULT_IMAGE_HANDLE.typed::<Image>().id(),
+        flip_x: false,
+        flip_y: false,
+        anchor: Vec2::ZERO,
+    }
+}
+
+#[allow(clippy::type_complexity)]
+pub fn extract_tilemap_debug<T>(
+    mut extracted_sprites: ResMut<ExtractedSprites>,
+    mut lines: Local<Vec<(Vec2, Vec2, Color)>>,
+    settings: Extract<Res<TilemapDebugSettings>>,
+    tilemap_query: Extract<
+        Query<(
+            Entity,
+            &T,
+            &TilemapGeometry,
+            &TilemapView,
+            Option<&TilemapCellSizes>,
+            &GlobalTransform,
+            &ComputedVisibility,
+        )>,
+    >,
+) where
+    T: Component + IndexableGrid,
+{
+    if !settings.enabled {
+        return;
+    }
+    for (entity, tilemap, geometry, view, cell_sizes, transform, visibility) in tilemap_query.iter()
+    {
+        if !visibility.is_visible() {
+            continue;
+        }
+        let map_size = [tilemap.width(), tilemap.height()];
+        lines.clear();
+        debug_lines(map_size, geometry, view, cell_sizes, &settings, &mut lines);
+        for &(a, b, color) in lines.iter() {
+            let [a, b] = [a, b].map(|point| {
+                transform.transform_point(point.extend(0.)) + settings.depth * Vec3::Z
+            });
+            extracted_sprites.sprites.alloc().init(line_sprite(
+                entity,
+                a,
+                b,
+                settings.line_width,
+                color,
+            ));
+        }
+    }
+}
+
+/// Toggles the overlay with the [`TilemapDebugSettings::toggle_key`]
+pub fn toggle_tilemap_debug(
+    keyboard: Option<Res<Input<KeyCode>>>,
+    mut settings: ResMut<TilemapDebugSettings>,
+) {
+    if let (Some(keyboard), Some(key)) = (keyboard, settings.toggle_key) {
+        if keyboard.just_pressed(key) {
+            settings.enabled = !settings.enabled;
+        }
+    }
+}
+
+/// Spawns and despawns the cell labels of each tilemap as the settings and the layouts
+/// of the tilemaps change. Editing tiles without resizing the tilemap keeps its labels.
+#[allow(clippy::type_complexity)]
+pub fn update_tilemap_debug_labels<T>(
+    mut commands: Commands,
+    mut map_sizes: Local<HashMap<Entity, [usize; 2]>>,
+    settings: Res<TilemapDebugSettings>,
+    tilemap_query: Query<(
+        Entity,
+        &T,
+        &TilemapGeometry,
+        &TilemapView,
+        Option<&TilemapCellSizes>,
+        Option<&TilemapDebugLabels>,
+    )>,
+    changed_query: Query<
+        Entity,
+        Or<(
+            Changed<TilemapGeometry>,
+            Changed<TilemapView>,
+            Changed<TilemapCellSizes>,
+        )>,
+    >,
+) where
+    T: Component + IndexableGrid,
+{
+    let font = match &settings.labels {
+        Some(font) if settings.enabled => Some(font),
+        _ => None,
+    };
+    map_sizes.retain(|&entity, _| tilemap_query.contains(entity));
+    for (entity, tilemap, geometry, view, cell_sizes, labels) in tilemap_query.iter() {
+        let map_size = [tilemap.width(), tilemap.height()];
+        let resized = map_sizes.insert(entity, map_size) != Some(map_size);
+        let rebuild = settings.is_changed()
+            || resized
+            || changed_query.contains(entity)
+            || (font.is_some() && labels.is_none());
+        if !rebuild {
+            continue;
+        }
+        if let Some(labels) = labels {
+            for &label in &labels.labels {
+                commands.entity(label).despawn_recursive();
+            }
+        }
+        let cells = match font {
+            Some(font) => {
+                let cells = drawn_cells(map_size, geometry, view, cell_sizes);
+                (cells.len() <= settings.max_labels).then_some((font, cells))
+            }
+            None => None,
+        };
+        let (font, cells) = match cells {
+            Some(cells) => cells,
+            None => {
+                if labels.is_some() {
+                    commands.entity(entity).remove::<TilemapDebugLabels>();
+                }
+                continue;
+            }
+        };
+        let style = TextStyle {
+            font: font.clone(),
+            font_size: settings.label_size,
+            color: settings.label_color,
+        };
+        let mut labels = Vec::with_capacity(cells.len());
+        commands.entity(entity).with_children(|parent| {
+            for ([x, y], centre, _) in cells {
+                labels.push(
+                    parent
+                        .spawn(Text2dBundle {
+                            text: Text::from_section(format!("{x},{y}"), style.clone())
+                                .with_alignment(TextAlignment::CENTER),
+                            transform: Transform::from_translation(centre.extend(settings.depth)),
+                            ..Default::default()
+                        })
+                        .id(),
+                );
+            }
+        });
+        commands
+            .entity(entity)
+            .insert(TilemapDebugLabels { labels });
+    }
+}
+
+/// Adds the debug overlay for tilemap components of type `T`.
+/// Add one for each of your own tilemap types alongside the [`TilemapDebugPlugin`].
+pub struct TilemapDebugOverlayPlugin<T>(PhantomData<fn() -> T>);
+
+impl<T> Default for TilemapDebugOverlayPlugin<T> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<T> Plugin for TilemapDebugOverlayPlugin<T>
+where
+    T: Component + IndexableGrid,
+{
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TilemapDebugSettings>()
+            .add_system(update_tilemap_debug_labels::<T>);
+        if let Ok(render_app) = app.get_sub_app_mut(RenderApp) {
+            render_app.add_system_to_stage(
+                RenderStage::Extract,
+                extract_tilemap_debug::<T>
+                    .after(TilemapRenderSystem::ExtractTiles)
+                    .after(SpriteSystem::ExtractSprites),
+            );
+        }
+    }
+}
+
+/// Draws grid lines, bounds, anchor points, view sections and cell labels over every
+/// [`Tilemap`] of the tile types drawn by [`SpriteTilemapPlugin`](crate::SpriteTilemapPlugin),
+/// as set by the [`TilemapDebugSettings`] resource.
+/// [`HashTilemap`](crate::hash_tilemap::HashTilemap)s have no fixed grid and aren't covered.
+///
+/// Add a [`TilemapDebugOverlayPlugin`] for each of your own tilemap types.
+pub struct TilemapDebugPlugin;
+
+impl Plugin for TilemapDebugPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TilemapDebugSettings>()
+            .add_system(toggle_tilemap_debug)
+            .add_plugin(TilemapDebugOverlayPlugin::<Tilemap<TextureAtlasTile>>::default())
+            .add_plugin(TilemapDebugOverlayPlugin::<Tilemap<Option<TextureAtlasTile>>>::default())
+            .add_plugin(TilemapDebugOverlayPlugin::<Tilemap<SpriteTile>>::default())
+            .add_plugin(TilemapDebugOverlayPlugin::<Tilemap<Option<SpriteTile>>>::default())
+            .add_plugin(TilemapDebugOverlayPlugin::<Tilemap<CompactTile>>::default())
+            .add_plugin(TilemapDebugOverlayPlugin::<Tilemap<Option<CompactTile>>>::default());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines_only(grid: bool, bounds: bool, view: bool, anchor: bool) -> TilemapDebugSettings {
+        let show = |shown: bool| shown.then_some(Color::WHITE);
+        TilemapDebugSettings {
+            grid: show(grid),
+            bounds: show(bounds),
+            view: show(view),
+            anchor: show(anchor),
+            ..Default::default()
+        }
+    }
+
+    fn overlay_lines(
+        map_size: [usize; 2],
+        geometry: &TilemapGeometry,
+        view: &TilemapView,
+        cell_sizes: Option<&TilemapCellSizes>,
+        settings: &TilemapDebugSettings,
+    ) -> Vec<(Vec2, Vec2, Color)> {
+        let mut lines = vec![];
+        debug_lines(map_size, geometry, view, cell_sizes, settings, &mut lines);
+        lines
+    }
+
+    #[test]
+    fn grid_lines_follow_cell_edges() {
+        let geometry = TilemapGeometry::default();
+        let lines = overlay_lines(
+            [3, 2],
+            &geometry,
+            &TilemapView::All,
+            None,
+            &lines_only(true, false, false, false),
+        );
+        assert_eq!(lines.len(), 4 + 3);
+        assert!(lines.contains(&(vec2(-24., -16.), vec2(-24., 16.), Color::WHITE)));
+        assert!(lines.contains(&(vec2(-24., 0.), vec2(24., 0.), Color::WHITE)));
+
+        let spaced = TilemapGeometry {
+            spacing: vec2(2., 0.),
+            ..Default::default()
+        };
+        let lines = overlay_lines(
+            [3, 2],
+            &spaced,
+            &TilemapView::All,
+            None,
+            &lines_only(true, false, false, false),
+        );
+        assert_eq!(lines.len(), 6 + 3);
+    }
+
+    #[test]
+    fn view_outline_is_drawn_for_sections() {
+        let geometry = TilemapGeometry::default();
+        let settings = lines_only(false, true, true, true);
+        let all = overlay_lines([4, 4], &geometry, &TilemapView::All, None, &settings);
+        assert_eq!(all.len(), 4 + 2);
+        let section = TilemapView::Section {
+            x: 1,
+            y: 1,
+            width: 2,
+            height: 2,
+        };
+        let lines = overlay_lines([4, 4], &geometry, &section, None, &settings);
+        assert_eq!(lines.len(), 4 + 4 + 2);
+    }
+
+    #[test]
+    fn labels_are_kept_while_tiles_are_edited() {
+        let mut world = World::new();
+        world.insert_resource(TilemapDebugSettings {
+            enabled: true,
+            labels: Some(Handle::default()),
+            ..Default::default()
+        });
+        let tilemap = world
+            .spawn((
+                Tilemap::<TextureAtlasTile>::from_default(2, 2),
+                TilemapGeometry::default(),
+                TilemapView::All,
+            ))
+            .id();
+        let mut stage =
+            SystemStage::single(update_tilemap_debug_labels::<Tilemap<TextureAtlasTile>>);
+        let mut run = |world: &mut World| {
+            stage.run(world);
+            world
+                .get::<TilemapDebugLabels>(tilemap)
+                .unwrap()
+                .labels
+                .clone()
+        };
+        let labels = run(&mut world);
+        assert_eq!(labels.len(), 4);
+
+        world.get_mut::<Tilemap<TextureAtlasTile>>(tilemap).unwrap()[[1, 1]] =
+            TextureAtlasTile::new(3);
+        assert_eq!(run(&mut world), labels);
+
+        world
+            .get_mut::<Tilemap<TextureAtlasTile>>(tilemap)
+            .unwrap()
+            .resize(3, 2, TextureAtlasTile::default());
+        let resized = run(&mut world);
+        assert_eq!(resized.len(), 6);
+        assert!(labels
+            .iter()
+            .all(|&label| world.get_entity(label).is_none()));
+    }
+}
@@ -0,0 +1,565 @@
+use crate::indexing::IndexableGrid;
+use crate::tile::Tileable;
+use crate::tilemap::Tilemap;
+use bevy::math::ivec2;
+use bevy::prelude::*;
+use std::ops::RangeInclusive;
+
+/// Shape stamped at every cell of a stroke
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Brush {
+    /// a single cell
+    Point,
+    /// a square with sides `2 * radius + 1` cells long
+    Square(u32),
+    /// a disc of cells within a radius of the centre
+    Circle(u32),
+}
+
+impl Brush {
+    /// Number of cells the brush reaches from its centre
+    #[inline]
+    pub fn radius(self) -> u32 {
+        match self {
+            Brush::Point => 0,
+            Brush::Square(radius) | Brush::Circle(radius) => radius,
+        }
+    }
+}
+
+/// Every row a span can be on
+const ALL_ROWS: [i32; 2] = [i32::MIN, i32::MAX];
+
+/// Clamps `value` to the range of `i32`
+#[inline]
+fn saturate(value: i64) -> i32 {
+    value.clamp(i32::MIN as i64, i32::MAX as i64) as i32
+}
+
+/// Offsets from `center` of the rows within `radius` of it that are inside `rows`
+#[inline]
+fn clipped_rows(center: i32, radius: i64, rows: [i32; 2]) -> RangeInclusive<i64> {
+    let first = (-radius).max(rows[0] as i64 - center as i64);
+    let last = radius.min(rows[1] as i64 - center as i64);
+    first..=last
+}
+
+/// Offset of step `i` of the `n` steps of a line covering `d` cells, rounded to the nearest cell
+#[inline]
+fn line_offset(d: i64, i: i64, n: i64) -> i64 {
+    if n == 0 {
+        return 0;
+    }
+    let rounded = (2 * d.unsigned_abs() as i128 * i as i128 + n as i128) / (2 * n as i128);
+    if d < 0 {
+        -(rounded as i64)
+    } else {
+        rounded as i64
+    }
+}
+
+/// The first of the steps `0..=n` for which `pred` is true, or `n + 1` if there is none.
+/// `pred` must be false for all steps before the first it is true for.
+fn first_step(n: i64, pred: impl Fn(i64) -> bool) -> i64 {
+    let (mut low, mut high) = (0, n + 1);
+    while low < high {
+        let mid = low + (high - low) / 2;
+        if pred(mid) {
+            high = mid;
+        } else {
+            low = mid + 1;
+        }
+    }
+    low
+}
+
+/// The steps of a line along which the coordinate starting at `start` and covering `d` cells
+/// is within `min..=max`
+fn steps_within(start: i64, d: i64, n: i64, [min, max]: [i64; 2]) -> RangeInclusive<i64> {
+    let at = |i| start + line_offset(d, i, n);
+    if 0 <= d {
+        first_step(n, |i| min <= at(i))..=first_step(n, |i| max < at(i)) - 1
+    } else {
+        first_step(n, |i| at(i) <= max)..=first_step(n, |i| at(i) < min) - 1
+    }
+}
+
+/// Calls `plot` for each cell of the line between `from` and `to` within `min..=max`, inclusive
+fn line_within(from: IVec2, to: IVec2, min: [i64; 2], max: [i64; 2], mut plot: impl FnMut(IVec2)) {
+    let d = [to.x as i64 - from.x as i64, to.y as i64 - from.y as i64];
+    let n = d[0].abs().max(d[1].abs());
+    let xs = steps_within(from.x as i64, d[0], n, [min[0], max[0]]);
+    let ys = steps_within(from.y as i64, d[1], n, [min[1], max[1]]);
+    for i in *xs.start().max(ys.start())..=*xs.end().min(ys.end()) {
+        plot(ivec2(
+            (from.x as i64 + line_offset(d[0], i, n)) as i32,
+            (from.y as i64 + line_offset(d[1], i, n)) as i32,
+        ));
+    }
+}
+
+/// Calls `plot` for each cell of the line between `from` and `to`, inclusive
+pub fn line(from: IVec2, to: IVec2, plot: impl FnMut(IVec2)) {
+    let min = [i32::MIN as i64; 2];
+    let max = [i32::MAX as i64; 2];
+    line_within(from, to, min, max, plot);
+}
+
+/// Half width of the row `dy` cells from the centre of an ellipse
+#[inline]
+fn ellipse_half_width(radii: [i64; 2], dy: i64) -> i64 {
+    let t = dy as f64 / (radii[1] as f64 + 0.5);
+    ((radii[0] as f64 + 0.5) * (1. - t * t).max(0.).sqrt()) as i64
+}
+
+#[inline]
+fn ellipse_radii(radii: IVec2) -> [i64; 2] {
+    [radii.x.unsigned_abs() as i64, radii.y.unsigned_abs() as i64]
+}
+
+fn ellipse_spans_within(
+    center: IVec2,
+    radii: [i64; 2],
+    rows: [i32; 2],
+    mut span: impl FnMut(i32, i32, i32),
+) {
+    let x = center.x as i64;
+    for dy in clipped_rows(center.y, radii[1], rows) {
+        let w = ellipse_half_width(radii, dy);
+        span(
+            (center.y as i64 + dy) as i32,
+            saturate(x - w),
+            saturate(x + w),
+        );
+    }
+}
+
+/// Calls `span` with the row and the first and last columns of each row of a filled ellipse
+pub fn ellipse_spans(center: IVec2, radii: IVec2, span: impl FnMut(i32, i32, i32)) {
+    ellipse_spans_within(center, ellipse_radii(radii), ALL_ROWS, span);
+}
+
+fn ellipse_outline_spans_within(
+    center: IVec2,
+    radii: [i64; 2],
+    rows: [i32; 2],
+    mut span: impl FnMut(i32, i32, i32),
+) {
+    let x = center.x as i64;
+    for dy in clipped_rows(center.y, radii[1], rows) {
+        let y = (center.y as i64 + dy) as i32;
+        let w = ellipse_half_width(radii, dy);
+        let inner = if dy.abs() < radii[1] {
+            ellipse_half_width(radii, dy.abs() + 1)
+        } else {
+            -1
+        };
+        let first = (inner + 1).min(w);
+        if first == 0 {
+            span(y, saturate(x - w), saturate(x + w));
+        } else {
+            span(y, saturate(x - w), saturate(x - first));
+            span(y, saturate(x + first), saturate(x + w));
+        }
+    }
+}
+
+/// Calls `span` with the row and the first and last columns of each run of cells
+/// on the outline of an ellipse
+pub fn ellipse_outline_spans(center: IVec2, radii: IVec2, span: impl FnMut(i32, i32, i32)) {
+    ellipse_outline_spans_within(center, ellipse_radii(radii), ALL_ROWS, span);
+}
+
+fn polygon_spans_within(points: &[IVec2], rows: [i32; 2], mut span: impl FnMut(i32, i32, i32)) {
+    if points.len() < 3 {
+        return;
+    }
+    let min_y = points
+        .iter()
+        .map(|point| point.y)
+        .min()
+        .unwrap()
+        .max(rows[0]);
+    let max_y = points
+        .iter()
+        .map(|point| point.y)
+        .max()
+        .unwrap()
+        .min(rows[1]);
+    let mut crossings = Vec::new();
+    for y in min_y..=max_y {
+        crossings.clear();
+        for (i, &a) in points.iter().enumerate() {
+            let b = points[(i + 1) % points.len()];
+            if (a.y <= y) != (b.y <= y) {
+                let t = (y as i64 - a.y as i64) as f64 / (b.y as i64 - a.y as i64) as f64;
+                crossings.push(a.x as f64 + t * (b.x as i64 - a.x as i64) as f64);
+            }
+        }
+        crossings.sort_by(|a, b| a.total_cmp(b));
+        for pair in crossings.chunks_exact(2) {
+            let first = saturate(pair[0].ceil() as i64);
+            let last = saturate(pair[1].floor() as i64);
+            if first <= last {
+                span(y, first, last);
+            }
+        }
+    }
+}
+
+/// Calls `span` with the row and the first and last columns of each run of cells
+/// inside a polygon, using the even-odd rule.
+/// Cells on the edges of the polygon are not guaranteed to be included.
+pub fn polygon_spans(points: &[IVec2], span: impl FnMut(i32, i32, i32)) {
+    polygon_spans_within(points, ALL_ROWS, span);
+}
+
+fn brush_spans_within(
+    center: IVec2,
+    brush: Brush,
+    rows: [i32; 2],
+    mut span: impl FnMut(i32, i32, i32),
+) {
+    let radius = brush.radius() as i64;
+    match brush {
+        Brush::Point => {
+            if rows[0] <= center.y && center.y <= rows[1] {
+                span(center.y, center.x, center.x)
+            }
+        }
+        Brush::Square(_) => {
+            let x = center.x as i64;
+            for dy in clipped_rows(center.y, radius, rows) {
+                let y = (center.y as i64 + dy) as i32;
+                span(y, saturate(x - radius), saturate(x + radius));
+            }
+        }
+        Brush::Circle(_) => ellipse_spans_within(center, [radius; 2], rows, span),
+    }
+}
+
+/// Calls `span` with the row and the first and last columns of each row of a brush
+pub fn brush_spans(center: IVec2, brush: Brush, span: impl FnMut(i32, i32, i32)) {
+    brush_spans_within(center, brush, ALL_ROWS, span);
+}
+
+impl<T> Tilemap<T>
+where
+    T: Tileable,
+{
+    /// The rows of the tilemap, first and last
+    #[inline]
+    fn rows(&self) -> [i32; 2] {
+        [0, saturate(self.height() as i64 - 1)]
+    }
+
+    /// The first and last cells lines are clipped to, `reach` cells outside of the tilemap
+    #[inline]
+    fn line_bounds(&self, reach: u32) -> ([i64; 2], [i64; 2]) {
+        let reach = reach as i64;
+        let max = [
+            self.width() as i64 - 1 + reach,
+            self.height() as i64 - 1 + reach,
+        ];
+        ([-reach; 2], max)
+    }
+
+    #[inline]
+    fn draw_cell(&mut self, point: IVec2, f: &mut impl FnMut(usize, usize) -> T) {
+        if point.x < 0 || point.y < 0 {
+            return;
+        }
+        let (x, y) = (point.x as usize, point.y as usize);
+        if let Some(index) = self.index_grid_checked(x, y) {
+            self[index] = f(x, y);
+        }
+    }
+
+    #[inline]
+    fn draw_span(&mut self, y: i32, first: i32, last: i32, f: &mut impl FnMut(usize, usize) -> T) {
+        if y < 0 || self.height() as i32 <= y {
+            return;
+        }
+        let first = first.max(0);
+        let last = last.min(self.width() as i32 - 1);
+        let y = y as usize;
+        for x in first..=last {
+            let x = x as usize;
+            let index = self.index_grid(x, y);
+            self[index] = f(x, y);
+        }
+    }
+
+    /// Set the cells of the line between `from` and `to`
+    pub fn draw_line(&mut self, from: IVec2, to: IVec2, tile: T) {
+        self.draw_line_fn(from, to, |_, _| tile.clone());
+    }
+
+    /// Set the cells of the line between `from` and `to` to values returned by `f`
+    pub fn draw_line_fn(&mut self, from: IVec2, to: IVec2, mut f: impl FnMut(usize, usize) -> T) {
+        let (min, max) = self.line_bounds(0);
+        line_within(from, to, min, max, |point| self.draw_cell(point, &mut f));
+    }
+
+    /// Stamp `brush` along the line between `from` and `to`
+    pub fn draw_stroke(&mut self, from: IVec2, to: IVec2, brush: Brush, tile: T) {
+        self.draw_stroke_fn(from, to, brush, |_, _| tile.clone());
+    }
+
+    /// Stamp `brush` along the line between `from` and `to`, with tiles returned by `f`
+    pub fn draw_stroke_fn(
+        &mut self,
+        from: IVec2,
+        to: IVec2,
+        brush: Brush,
+        mut f: impl FnMut(usize, usize) -> T,
+    ) {
+        let rows = self.rows();
+        let (min, max) = self.line_bounds(brush.radius());
+        line_within(from, to, min, max, |point| {
+            brush_spans_within(point, brush, rows, |y, first, last| {
+                self.draw_span(y, first, last, &mut f)
+            })
+        });
+    }
+
+    /// Stamp `brush` centred on `center`
+    pub fn draw_brush(&mut self, center: IVec2, brush: Brush, tile: T) {
+        self.draw_brush_fn(center, brush, |_, _| tile.clone());
+    }
+
+    /// Stamp `brush` centred on `center`, with tiles returned by `f`
+    pub fn draw_brush_fn(
+        &mut self,
+        center: IVec2,
+        brush: Brush,
+        mut f: impl FnMut(usize, usize) -> T,
+    ) {
+        let rows = self.rows();
+        brush_spans_within(center, brush, rows, |y, first, last| {
+            self.draw_span(y, first, last, &mut f)
+        });
+    }
+
+    /// Set every cell of the rectangle with opposite corners `a` and `b`
+    pub fn fill_rect(&mut self, a: IVec2, b: IVec2, tile: T) {
+        self.fill_rect_fn(a, b, |_, _| tile.clone());
+    }
+
+    /// Set every cell of the rectangle with opposite corners `a` and `b` to values returned by `f`
+    pub fn fill_rect_fn(&mut self, a: IVec2, b: IVec2, mut f: impl FnMut(usize, usize) -> T) {
+        let min = a.min(b);
+        let max = a.max(b);
+        for y in min.y.max(0)..=max.y.min(self.height() as i32 - 1) {
+            self.draw_span(y, min.x, max.x, &mut f);
+        }
+    }
+
+    /// Set the cells on the edges of the rectangle with opposite corners `a` and `b`
+    pub fn draw_rect(&mut self, a: IVec2, b: IVec2, tile: T) {
+        self.draw_rect_fn(a, b, |_, _| tile.clone());
+    }
+
+    /// Set the cells on the edges of the rectangle with opposite corners `a` and `b`
+    /// to values returned by `f`
+    pub fn draw_rect_fn(&mut self, a: IVec2, b: IVec2, mut f: impl FnMut(usize, usize) -> T) {
+        let min = a.min(b);
+        let max = a.max(b);
+        self.draw_span(min.y, min.x, max.x, &mut f);
+        if min.y < max.y {
+            self.draw_span(max.y, min.x, max.x, &mut f);
+        }
+        for y in min.y.saturating_add(1).max(0)..max.y.min(self.rows()[1].saturating_add(1)) {
+            self.draw_cell(ivec2(min.x, y), &mut f);
+            if min.x < max.x {
+                self.draw_cell(ivec2(max.x, y), &mut f);
+            }
+        }
+    }
+
+    /// Set every cell of the ellipse centred on `center` with radii `radii`
+    pub fn fill_ellipse(&mut self, center: IVec2, radii: IVec2, tile: T) {
+        self.fill_ellipse_fn(center, radii, |_, _| tile.clone());
+    }
+
+    /// Set every cell of the ellipse centred on `center` with radii `radii`
+    /// to values returned by `f`
+    pub fn fill_ellipse_fn(
+        &mut self,
+        center: IVec2,
+        radii: IVec2,
+        mut f: impl FnMut(usize, usize) -> T,
+    ) {
+        let rows = self.rows();
+        ellipse_spans_within(center, ellipse_radii(radii), rows, |y, first, last| {
+            self.draw_span(y, first, last, &mut f)
+        });
+    }
+
+    /// Set the cells on the outline of the ellipse centred on `center` with radii `radii`
+    pub fn draw_ellipse(&mut self, center: IVec2, radii: IVec2, tile: T) {
+        self.draw_ellipse_fn(center, radii, |_, _| tile.clone());
+    }
+
+    /// Set the cells on the outline of the ellipse centred on `center` with radii `radii`
+    /// to values returned by `f`
+    pub fn draw_ellipse_fn(
+        &mut self,
+        center: IVec2,
+        radii: IVec2,
+        mut f: impl FnMut(usize, usize) -> T,
+    ) {
+        let rows = self.rows();
+        ellipse_outline_spans_within(center, ellipse_radii(radii), rows, |y, first, last| {
+            self.draw_span(y, first, last, &mut f)
+        });
+    }
+
+    /// Set every cell inside or on the edges of the polygon with vertices `points`
+    pub fn fill_polygon(&mut self, points: &[IVec2], tile: T) {
+        self.fill_polygon_fn(points, |_, _| tile.clone());
+    }
+
+    /// Set every cell inside or on the edges of the polygon with vertices `points`
+    /// to values returned by `f`
+    pub fn fill_polygon_fn(&mut self, points: &[IVec2], mut f: impl FnMut(usize, usize) -> T) {
+        let rows = self.rows();
+        polygon_spans_within(points, rows, |y, first, last| {
+            self.draw_span(y, first, last, &mut f)
+        });
+        self.draw_polygon_fn(points, f);
+    }
+
+    /// Set the cells on the edges of the polygon with vertices `points`
+    pub fn draw_polygon(&mut self, points: &[IVec2], tile: T) {
+        self.draw_polygon_fn(points, |_, _| tile.clone());
+    }
+
+    /// Set the cells on the edges of the polygon with vertices `points`
+    /// to values returned by `f`
+    pub fn draw_polygon_fn(&mut self, points: &[IVec2], mut f: impl FnMut(usize, usize) -> T) {
+        let (min, max) = self.line_bounds(0);
+        for (i, &from) in points.iter().enumerate() {
+            let to = points[(i + 1) % points.len()];
+            line_within(from, to, min, max, |point| self.draw_cell(point, &mut f));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Clone, Debug, Default, PartialEq)]
+    struct Cell(bool);
+
+    impl Tileable for Cell {}
+
+    fn count(tilemap: &Tilemap<Cell>) -> usize {
+        tilemap.into_iter().filter(|cell| cell.0).count()
+    }
+
+    #[test]
+    fn draw_line() {
+        let mut tilemap = Tilemap::<Cell>::from_default(10, 10);
+        tilemap.draw_line(ivec2(0, 0), ivec2(9, 3), Cell(true));
+        assert_eq!(count(&tilemap), 10);
+        assert!(tilemap[[0, 0]].0);
+        assert!(tilemap[[9, 3]].0);
+
+        let mut points = vec![];
+        line(ivec2(2, 5), ivec2(2, 1), |point| points.push(point));
+        assert_eq!(points.len(), 5);
+        assert_eq!(points[4], ivec2(2, 1));
+    }
+
+    #[test]
+    fn clip_to_bounds() {
+        let mut tilemap = Tilemap::<Cell>::from_default(4, 3);
+        tilemap.fill_rect(ivec2(-5, -5), ivec2(100, 1), Cell(true));
+        assert_eq!(count(&tilemap), 8);
+
+        let mut tilemap = Tilemap::<Cell>::from_default(4, 3);
+        tilemap.draw_line(ivec2(-3, -3), ivec2(10, 10), Cell(true));
+        assert_eq!(count(&tilemap), 3);
+
+        let mut tilemap = Tilemap::<Cell>::from_default(4, 3);
+        tilemap.fill_ellipse(ivec2(-10, 1), ivec2(3, 3), Cell(true));
+        assert_eq!(count(&tilemap), 0);
+    }
+
+    #[test]
+    fn far_off_shapes_are_clipped() {
+        let mut tilemap = Tilemap::<Cell>::from_default(4, 3);
+        tilemap.draw_line(
+            ivec2(i32::MIN, i32::MIN),
+            ivec2(i32::MAX, i32::MAX),
+            Cell(true),
+        );
+        assert_eq!(count(&tilemap), 3);
+        assert!(tilemap[[1, 1]].0);
+
+        let mut tilemap = Tilemap::<Cell>::from_default(4, 3);
+        tilemap.draw_stroke(
+            ivec2(i32::MIN, 1),
+            ivec2(i32::MAX, 1),
+            Brush::Square(1),
+            Cell(true),
+        );
+        assert_eq!(count(&tilemap), 12);
+
+        let mut tilemap = Tilemap::<Cell>::from_default(4, 3);
+        tilemap.draw_brush(ivec2(1, 1), Brush::Square(u32::MAX), Cell(true));
+        assert_eq!(count(&tilemap), 12);
+
+        let mut tilemap = Tilemap::<Cell>::from_default(4, 3);
+        tilemap.fill_ellipse(ivec2(1, 1), IVec2::splat(i32::MIN), Cell(true));
+        assert_eq!(count(&tilemap), 12);
+
+        let mut tilemap = Tilemap::<Cell>::from_default(4, 3);
+        tilemap.draw_rect(
+            ivec2(i32::MIN, i32::MIN),
+            ivec2(i32::MAX, i32::MAX),
+            Cell(true),
+        );
+        assert_eq!(count(&tilemap), 0);
+        tilemap.draw_rect(ivec2(i32::MIN, i32::MIN), ivec2(2, i32::MAX), Cell(true));
+        assert_eq!(count(&tilemap), 3);
+    }
+
+    #[test]
+    fn draw_rect() {
+        let mut tilemap = Tilemap::<Cell>::from_default(10, 10);
+        tilemap.draw_rect(ivec2(6, 7), ivec2(2, 3), Cell(true));
+        assert_eq!(count(&tilemap), 16);
+        assert!(!tilemap[[4, 5]].0);
+    }
+
+    #[test]
+    fn ellipse_is_symmetric() {
+        let mut tilemap = Tilemap::<Cell>::from_default(21, 21);
+        tilemap.draw_ellipse(ivec2(10, 10), ivec2(7, 4), Cell(true));
+        assert!(tilemap[[3, 10]].0);
+        assert!(tilemap[[17, 10]].0);
+        assert!(tilemap[[10, 14]].0);
+        assert!(!tilemap[[10, 10]].0);
+        for (x, y, cell) in tilemap.indexed_iter() {
+            assert_eq!(cell, &tilemap[[20 - x, y]]);
+            assert_eq!(cell, &tilemap[[x, 20 - y]]);
+        }
+    }
+
+    #[test]
+    fn fill_polygon() {
+        let mut tilemap = Tilemap::<Cell>::from_default(10, 10);
+        tilemap.fill_polygon(
+            &[ivec2(1, 1), ivec2(8, 1), ivec2(8, 8), ivec2(1, 8)],
+            Cell(true),
+        );
+        assert_eq!(count(&tilemap), 64);
+
+        let mut tilemap = Tilemap::<Cell>::from_default(10, 10);
+        tilemap.fill_polygon(&[ivec2(0, 0), ivec2(9, 0), ivec2(0, 9)], Cell(true));
+        assert_eq!(count(&tilemap), 55);
+    }
+}
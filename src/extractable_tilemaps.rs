@@ -1,84 +1,130 @@
 use crate::extraction::ExtractableAtlasTilemap;
 use crate::extraction::ExtractableTilemap;
 use crate::prelude::*;
+use crate::tile::offset_transform;
 use crate::tile::SpriteTile;
 use crate::tile::TextureAtlasTile;
 use bevy::prelude::*;
 use bevy::sprite::ExtractedSprite;
+use std::borrow::Cow;
 
-impl ExtractableAtlasTilemap for Tilemap<TextureAtlasTile> {
+/// Conversion from a tile to the [`TextureAtlasTile`] drawn for it.
+///
+/// Implement this for your own tile type and add an
+/// [`ExtractAtlasTilemapPlugin`](crate::extraction::ExtractAtlasTilemapPlugin)
+/// for `Tilemap<YourTile>` to draw it with a texture atlas.
+pub trait AsAtlasTile: Tileable {
+    /// The tile to draw, or `None` to leave the cell empty
+    fn as_atlas_tile(&self) -> Option<Cow<'_, TextureAtlasTile>>;
+}
+
+/// Conversion from a tile to the [`SpriteTile`] drawn for it.
+///
+/// Implement this for your own tile type and add an
+/// [`ExtractTilemapPlugin`](crate::extraction::ExtractTilemapPlugin)
+/// for `Tilemap<YourTile>` to draw it.
+pub trait AsSpriteTile: Tileable {
+    /// The tile to draw, or `None` to leave the cell empty
+    fn as_sprite_tile(&self) -> Option<Cow<'_, SpriteTile>>;
+}
+
+impl AsAtlasTile for TextureAtlasTile {
     #[inline]
-    fn extract_tile(
+    fn as_atlas_tile(&self) -> Option<Cow<'_, TextureAtlasTile>> {
+        Some(Cow::Borrowed(self))
+    }
+}
+
+impl<T> AsAtlasTile for Option<T>
+where
+    T: AsAtlasTile,
+{
+    #[inline]
+    fn as_atlas_tile(&self) -> Option<Cow<'_, TextureAtlasTile>> {
+        self.as_ref().and_then(AsAtlasTile::as_atlas_tile)
+    }
+}
+
+impl AsSpriteTile for SpriteTile {
+    #[inline]
+    fn as_sprite_tile(&self) -> Option<Cow<'_, SpriteTile>> {
+        Some(Cow::Borrowed(self))
+    }
+}
+
+impl<T> AsSpriteTile for Option<T>
+where
+    T: AsSpriteTile,
+{
+    #[inline]
+    fn as_sprite_tile(&self) -> Option<Cow<'_, SpriteTile>> {
+        self.as_ref().and_then(AsSpriteTile::as_sprite_tile)
+    }
+}
+
+impl TextureAtlasTile {
+    /// Sprite drawn for this tile in the cell with transform `transform`
+    #[inline]
+    pub fn extract(
         &self,
         entity: Entity,
         transform: GlobalTransform,
         texture_atlas: &TextureAtlas,
-        index: usize,
-    ) -> Option<ExtractedSprite> {
-        let sprite = &self[index];
+    ) -> ExtractedSprite {
         ExtractedSprite {
             entity,
-            transform,
-            color: sprite.color,
-            rect: Some(texture_atlas.textures[sprite.index]),
-            custom_size: sprite.custom_size,
+            transform: offset_transform(transform, self.offset, self.scale),
+            color: self.color,
+            rect: Some(texture_atlas.textures[self.index]),
+            custom_size: self.custom_size,
             image_handle_id: texture_atlas.texture.id(),
-            flip_x: sprite.flip_x,
-            flip_y: sprite.flip_y,
-            anchor: sprite.anchor.as_vec(),
+            flip_x: self.flip_x,
+            flip_y: self.flip_y,
+            anchor: self.anchor.as_vec(),
         }
-        .into()
     }
 }
 
-impl ExtractableAtlasTilemap for Tilemap<Option<TextureAtlasTile>> {
+impl SpriteTile {
+    /// Sprite drawn for this tile in the cell with transform `transform`
     #[inline]
-    fn extract_tile(
-        &self,
-        entity: Entity,
-        transform: GlobalTransform,
-        texture_atlas: &TextureAtlas,
-        index: usize,
-    ) -> Option<ExtractedSprite> {
-        self[index].as_ref().map(|sprite| ExtractedSprite {
+    pub fn extract(&self, entity: Entity, transform: GlobalTransform) -> ExtractedSprite {
+        ExtractedSprite {
             entity,
-            transform,
-            color: sprite.color,
-            rect: Some(texture_atlas.textures[sprite.index]),
-            custom_size: sprite.custom_size,
-            image_handle_id: texture_atlas.texture.id(),
-            flip_x: sprite.flip_x,
-            flip_y: sprite.flip_y,
-            anchor: sprite.anchor.as_vec(),
-        })
+            transform: offset_transform(transform, self.offset, self.scale),
+            color: self.color,
+            rect: None,
+            custom_size: self.custom_size,
+            image_handle_id: self.texture.id(),
+            flip_x: self.flip_x,
+            flip_y: self.flip_y,
+            anchor: self.anchor.as_vec(),
+        }
     }
 }
 
-impl ExtractableTilemap for Tilemap<SpriteTile> {
+impl<T> ExtractableAtlasTilemap for Tilemap<T>
+where
+    T: AsAtlasTile,
+{
     #[inline]
     fn extract_tile(
         &self,
         entity: Entity,
         transform: GlobalTransform,
+        texture_atlas: &TextureAtlas,
         index: usize,
     ) -> Option<ExtractedSprite> {
-        let sprite = &self[index];
-        ExtractedSprite {
-            entity,
-            transform,
-            color: sprite.color,
-            rect: None,
-            custom_size: sprite.custom_size,
-            image_handle_id: sprite.texture.id(),
-            flip_x: sprite.flip_x,
-            flip_y: sprite.flip_y,
-            anchor: sprite.anchor.as_vec(),
-        }
-        .into()
+        self[index]
+            .as_atlas_tile()
+            .map(|tile| tile.extract(entity, transform, texture_atlas))
     }
 }
 
-impl ExtractableTilemap for Tilemap<Option<SpriteTile>> {
+impl<T> ExtractableTilemap for Tilemap<T>
+where
+    T: AsSpriteTile,
+{
     #[inline]
     fn extract_tile(
         &self,
@@ -86,16 +132,8 @@ impl ExtractableTilemap for Tilemap<Option<SpriteTile>> {
         transform: GlobalTransform,
         index: usize,
     ) -> Option<ExtractedSprite> {
-        self[index].as_ref().map(|sprite| ExtractedSprite {
-            entity,
-            transform,
-            color: sprite.color,
-            rect: None,
-            custom_size: sprite.custom_size,
-            image_handle_id: sprite.texture.id(),
-            flip_x: sprite.flip_x,
-            flip_y: sprite.flip_y,
-            anchor: sprite.anchor.as_vec(),
-        })
+        self[index]
+            .as_sprite_tile()
+            .map(|tile| tile.extract(entity, transform))
     }
 }
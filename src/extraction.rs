@@ -1,18 +1,30 @@
+use crate::cell_sizes::TilemapCellSizes;
+use crate::compact::CompactTile;
+use crate::compact::ExtractCompactTilemapPlugin;
 use crate::geometry::*;
+use crate::hash_tilemap::ExtractHashAtlasTilemapPlugin;
+use crate::hash_tilemap::ExtractHashTilemapPlugin;
 use crate::prelude::IndexableGrid;
 use crate::prelude::Tilemap;
+use crate::shared::calculate_shared_tilemap_bounds;
+use crate::shared::extract_shared_atlas_tilemap;
+use crate::shared::extract_shared_tilemap;
 use crate::tile::SpriteTile;
 use crate::tile::TextureAtlasTile;
 use bevy::math::vec2;
 use bevy::math::Vec3A;
 use bevy::prelude::*;
+use bevy::render::primitives::Aabb;
+use bevy::render::view::VisibilitySystems;
 use bevy::render::Extract;
 use bevy::render::RenderApp;
 use bevy::render::RenderStage;
 use bevy::sprite::ExtractedSprite;
 use bevy::sprite::ExtractedSprites;
 use bevy::sprite::SpriteSystem;
+use bevy::tasks::ComputeTaskPool;
 use copyless::VecHelper;
+use std::marker::PhantomData;
 
 pub trait ExtractableAtlasTilemap: Component + IndexableGrid {
     fn extract_tile(
@@ -33,71 +45,416 @@ pub trait ExtractableTilemap: Component + IndexableGrid {
     ) -> Option<ExtractedSprite>;
 }
 
-fn iter_grid_coords(
+/// Cells are extracted this far past the edges of the cameras' views,
+/// so tiles that overhang their cells are not culled
+pub(crate) const CULLING_MARGIN: f32 = 1.0;
+
+/// The culling margin in local space, scaled by the largest cell of maps with cell sizes
+#[inline]
+fn culling_margin(geometry: &TilemapGeometry, cell_sizes: Option<&TilemapCellSizes>) -> Vec2 {
+    CULLING_MARGIN
+        * match cell_sizes {
+            Some(cell_sizes) => cell_sizes.largest_cell_size(),
+            None => geometry.tile_size.abs(),
+        }
+}
+
+pub(crate) fn iter_grid_coords(
     grid_width: usize,
     grid_height: usize,
-    view: &TilemapView,
+    window: ViewWindow,
     geometry: &TilemapGeometry,
     mut transform: GlobalTransform,
 ) -> impl Iterator<Item = (usize, GlobalTransform)> {
-    let [view_x, view_y, view_width, view_height] = view.clip([grid_width, grid_height]);
-    let grid_dimensions = vec2(grid_width as f32, grid_height as f32);
-    let grid_size = grid_dimensions * geometry.tile_size;
-    let grid_translation = transform.affine().transform_vector3(
-        (0.5 * geometry.tile_size - (0.5 + geometry.anchor.as_vec()) * grid_size
-            + vec2(
-                if geometry.reverse_rows {
-                    grid_size.x - geometry.tile_size.x
-                } else {
-                    0.0
-                },
-                if geometry.reverse_columns {
-                    grid_size.y - geometry.tile_size.y
-                } else {
-                    0.0
-                },
-            ))
-        .extend(0.),
-    );
-    let next_row_skip = grid_width - view_width;
-    let right = transform
-        .affine()
-        .transform_vector3(geometry.tile_size.x * Vec3::X);
-    let up = transform
+    let grid_translation = transform
         .affine()
-        .transform_vector3(geometry.tile_size.y * Vec3::Y);
-    let step_row: Vec3A = if geometry.reverse_rows { -1.0 } else { 1.0 } * Vec3A::from(right);
-    let step_column: Vec3A = if geometry.reverse_columns { -1.0 } else { 1.0 } * Vec3A::from(up);
-    let next_row_step: Vec3A = -step_row * view_width as f32 + step_column;
+        .transform_vector3(geometry.grid_origin(window.layout).extend(0.));
+    let step = geometry.cell_step();
+    let step_row = Vec3A::from(transform.affine().transform_vector3(step.x * Vec3::X));
+    let step_column = Vec3A::from(transform.affine().transform_vector3(step.y * Vec3::Y));
+    let next_row_step: Vec3A = -step_row * window.width as f32 + step_column;
     let view_translation =
-        Vec3A::from(grid_translation) + view_x as f32 * step_row + view_y as f32 * step_column;
-    *transform.translation_mut() += view_translation;
-    let mut cell_index = view_y * grid_width + view_x;
-    let mut x = 0;
-    (0..view_width * view_height).map(move |_| {
-        let out = (cell_index, transform);
+        Vec3A::from(grid_translation) + window.origin.x * step_row + window.origin.y * step_column;
+    let first_translation = Vec3A::from(transform.translation()) + view_translation;
+    *transform.translation_mut() = first_translation;
+    let placement = geometry.placement;
+    let mut x = window.x;
+    let mut y = window.y;
+    let mut column = 0;
+    let mut row = 0;
+    (0..window.width * window.height).map(move |_| {
+        let out = match placement {
+            CellPlacement::Accumulated => (y * grid_width + x, transform),
+            _ => {
+                let mut cell_transform = transform;
+                *cell_transform.translation_mut() = placement
+                    .snap(first_translation + column as f32 * step_row + row as f32 * step_column);
+                (y * grid_width + x, cell_transform)
+            }
+        };
         *transform.translation_mut() += step_row;
-        cell_index += 1;
         x += 1;
-        if x == view_width {
+        if x == grid_width {
             x = 0;
-            cell_index += next_row_skip;
+        }
+        column += 1;
+        if column == window.width {
+            column = 0;
+            row += 1;
+            x = window.x;
+            y += 1;
+            if y == grid_height {
+                y = 0;
+            }
             *transform.translation_mut() += next_row_step;
         }
         out
     })
 }
 
+/// Corners of the area of the world seen by each active camera
+pub(crate) fn camera_areas<'a>(
+    cameras: impl Iterator<Item = (&'a Camera, &'a GlobalTransform)>,
+) -> Vec<[Vec2; 4]> {
+    cameras
+        .filter(|(camera, _)| camera.is_active)
+        .map(|(camera, camera_transform)| camera_area(camera, camera_transform))
+        .collect()
+}
+
+/// Corners of the area of the world seen by a camera
+pub(crate) fn camera_area(camera: &Camera, camera_transform: &GlobalTransform) -> [Vec2; 4] {
+    let ndc_to_world = camera_transform.compute_matrix() * camera.projection_matrix().inverse();
+    [vec2(-1., -1.), vec2(1., -1.), vec2(1., 1.), vec2(-1., 1.)]
+        .map(|ndc| ndc_to_world.project_point3(ndc.extend(0.)).truncate())
+}
+
+/// The cells drawn by `view` that may be seen from within `areas`
+pub(crate) fn visible_window(
+    map_size: [usize; 2],
+    geometry: &TilemapGeometry,
+    view: &TilemapView,
+    cell_sizes: Option<&TilemapCellSizes>,
+    transform: &GlobalTransform,
+    areas: &[[Vec2; 4]],
+) -> ViewWindow {
+    let window = view.window(map_size);
+    if window.width == 0 || window.height == 0 || areas.is_empty() {
+        return window;
+    }
+    let world_to_local = transform.affine().inverse();
+    let z = transform.translation().z;
+    let mut min = Vec2::splat(f32::INFINITY);
+    let mut max = Vec2::splat(f32::NEG_INFINITY);
+    for corner in areas.iter().flatten() {
+        let local = world_to_local.transform_point3(corner.extend(z)).truncate();
+        min = min.min(local);
+        max = max.max(local);
+    }
+    match cell_sizes {
+        Some(cell_sizes) => {
+            // cells keep their own positions, so only views that don't wrap are culled
+            if map_size[0] < window.x + window.width || map_size[1] < window.y + window.height {
+                return window;
+            }
+            let margin = culling_margin(geometry, Some(cell_sizes));
+            let [columns, rows] = cell_sizes.cell_range(geometry, min - margin, max + margin);
+            let columns = columns.start.max(window.x)..columns.end.min(window.x + window.width);
+            let rows = rows.start.max(window.y)..rows.end.min(window.y + window.height);
+            ViewWindow {
+                x: columns.start,
+                y: rows.start,
+                width: columns.len(),
+                height: rows.len(),
+                origin: window.origin
+                    + vec2(
+                        (columns.start - window.x) as f32,
+                        (rows.start - window.y) as f32,
+                    ),
+                layout: window.layout,
+            }
+        }
+        None => {
+            let a = geometry.local_to_cell(window.layout, min);
+            let b = geometry.local_to_cell(window.layout, max);
+            let c = geometry.local_to_cell(window.layout, vec2(min.x, max.y));
+            let d = geometry.local_to_cell(window.layout, vec2(max.x, min.y));
+            let min = a.min(b).min(c).min(d);
+            let max = a.max(b).max(c).max(d);
+            window.restrict(map_size, min - CULLING_MARGIN, max + CULLING_MARGIN)
+        }
+    }
+}
+
+/// Every cell of a window in the order they are drawn
+#[inline]
+pub(crate) fn iter_window_cells(
+    map_size: [usize; 2],
+    window: ViewWindow,
+) -> impl Iterator<Item = [usize; 2]> {
+    (0..window.height).flat_map(move |j| {
+        (0..window.width).map(move |i| [(window.x + i) % map_size[0], (window.y + j) % map_size[1]])
+    })
+}
+
+/// Controls how tilemaps are extracted for rendering
+#[derive(Resource, Clone, Debug)]
+pub struct TilemapExtractionSettings {
+    /// Extract tilemaps in parallel when at least this many cells are visible in total.
+    /// Set to `usize::MAX` to always extract serially.
+    pub parallel_threshold: usize,
+    /// Number of cells extracted by each parallel task.
+    /// Large tilemaps are divided into bands of rows of about this many cells.
+    pub cells_per_task: usize,
+}
+
+impl Default for TilemapExtractionSettings {
+    fn default() -> Self {
+        Self {
+            parallel_threshold: 32_768,
+            cells_per_task: 8_192,
+        }
+    }
+}
+
+/// The cells of a tilemap to extract and how to extract each one
+pub(crate) struct ExtractionJob<'a, F> {
+    pub map_size: [usize; 2],
+    pub window: ViewWindow,
+    pub geometry: &'a TilemapGeometry,
+    pub cell_sizes: Option<&'a TilemapCellSizes>,
+    pub transform: GlobalTransform,
+    /// sizes of images drawn without an atlas rect, needed to fit them to their cells
+    pub images: Option<&'a Assets<Image>>,
+    pub extract_tile: F,
+}
+
+/// Size a sprite extracted for a cell of size `cell_size` to its cell.
+/// Sprites with a `custom_size` and sprites whose image size isn't known are left unchanged.
+/// Sprites turned nearer a quarter turn than not relative to their cell `cell_transform`
+/// are fitted with the cell's width and height swapped.
+pub(crate) fn fit_sprite(
+    sprite: &mut ExtractedSprite,
+    fit: TileFit,
+    cell_size: Vec2,
+    cell_transform: &GlobalTransform,
+    images: Option<&Assets<Image>>,
+) {
+    if fit == TileFit::Native || sprite.custom_size.is_some() {
+        return;
+    }
+    let source = match sprite.rect {
+        Some(rect) => rect,
+        None => match images.and_then(|images| images.get(&Handle::weak(sprite.image_handle_id))) {
+            Some(image) => Rect::from_corners(Vec2::ZERO, image.size()),
+            None => return,
+        },
+    };
+    let sprite_x = sprite.transform.affine().matrix3.x_axis;
+    let cell_axes = cell_transform.affine().matrix3;
+    let turned = sprite_x.dot(cell_axes.x_axis).abs() < sprite_x.dot(cell_axes.y_axis).abs();
+    let cell_size = if turned { cell_size.yx() } else { cell_size };
+    if let Some((rect, size)) = fit.fit(source, cell_size) {
+        sprite.rect = Some(rect);
+        sprite.custom_size = Some(size);
+    }
+}
+
+impl<'a, F> ExtractionJob<'a, F>
+where
+    F: Fn(usize, GlobalTransform) -> Option<ExtractedSprite>,
+{
+    fn extract(&self, window: ViewWindow, sprites: &mut Vec<ExtractedSprite>) {
+        if let Some(cell_sizes) = self.cell_sizes {
+            let [columns, rows] = cell_sizes.dimensions();
+            for [x, y] in iter_window_cells(self.map_size, window) {
+                if columns <= x || rows <= y {
+                    continue;
+                }
+                let local = cell_sizes.cell_to_local(self.geometry, [x, y]);
+                let mut transform = self.transform;
+                *transform.translation_mut() = self.geometry.placement.snap(
+                    self.transform
+                        .affine()
+                        .transform_point3a(local.extend(0.).into()),
+                );
+                if let Some(mut extracted_sprite) =
+                    (self.extract_tile)(y * self.map_size[0] + x, transform)
+                {
+                    fit_sprite(
+                        &mut extracted_sprite,
+                        self.geometry.fit,
+                        cell_sizes.cell_size([x, y]),
+                        &transform,
+                        self.images,
+                    );
+                    sprites.alloc().init(extracted_sprite);
+                }
+            }
+            return;
+        }
+        iter_grid_coords(
+            self.map_size[0],
+            self.map_size[1],
+            window,
+            self.geometry,
+            self.transform,
+        )
+        .for_each(|(index, transform)| {
+            if let Some(mut extracted_sprite) = (self.extract_tile)(index, transform) {
+                fit_sprite(
+                    &mut extracted_sprite,
+                    self.geometry.fit,
+                    self.geometry.tile_size,
+                    &transform,
+                    self.images,
+                );
+                sprites.alloc().init(extracted_sprite);
+            }
+        });
+    }
+}
+
+/// Extract the cells of every job, in parallel if there are enough of them
+pub(crate) fn run_extraction_jobs<F>(
+    sprites: &mut Vec<ExtractedSprite>,
+    jobs: &[ExtractionJob<F>],
+    settings: &TilemapExtractionSettings,
+) where
+    F: Fn(usize, GlobalTransform) -> Option<ExtractedSprite> + Sync,
+{
+    let total_cells: usize = jobs
+        .iter()
+        .map(|job| job.window.width * job.window.height)
+        .sum();
+    if total_cells < settings.parallel_threshold {
+        sprites.reserve(total_cells);
+        for job in jobs {
+            job.extract(job.window, sprites);
+        }
+        return;
+    }
+    let buffers = ComputeTaskPool::get().scope(|scope| {
+        for job in jobs {
+            let window = job.window;
+            let rows_per_task = (settings.cells_per_task / window.width.max(1)).max(1);
+            let mut first_row = 0;
+            while first_row < window.height {
+                let band = window.rows(job.map_size, first_row, rows_per_task);
+                scope.spawn(async move {
+                    let mut buffer = Vec::with_capacity(band.width * band.height);
+                    job.extract(band, &mut buffer);
+                    buffer
+                });
+                first_row += band.height;
+            }
+        }
+    });
+    sprites.reserve(buffers.iter().map(Vec::len).sum());
+    for mut buffer in buffers {
+        sprites.append(&mut buffer);
+    }
+}
+
+/// Extract the cells seen from within `areas` of each visible tilemap
+#[allow(clippy::type_complexity)]
+pub(crate) fn extract_visible_atlas_tilemaps<'a, T>(
+    sprites: &mut Vec<ExtractedSprite>,
+    areas: &[[Vec2; 4]],
+    settings: &TilemapExtractionSettings,
+    tilemaps: impl Iterator<
+        Item = (
+            Entity,
+            &'a T,
+            &'a TilemapGeometry,
+            &'a TilemapView,
+            Option<&'a TilemapCellSizes>,
+            &'a TextureAtlas,
+            &'a GlobalTransform,
+            bool,
+        ),
+    >,
+) where
+    T: ExtractableAtlasTilemap,
+{
+    let jobs: Vec<_> = tilemaps
+        .filter(|(.., visible)| *visible)
+        .map(
+            |(entity, tilemap, geometry, view, cell_sizes, texture_atlas, transform, _)| {
+                let map_size = [tilemap.width(), tilemap.height()];
+                ExtractionJob {
+                    map_size,
+                    window: visible_window(map_size, geometry, view, cell_sizes, transform, areas),
+                    geometry,
+                    cell_sizes,
+                    transform: *transform,
+                    images: None,
+                    extract_tile: move |index, transform| {
+                        tilemap.extract_tile(entity, transform, texture_atlas, index)
+                    },
+                }
+            },
+        )
+        .collect();
+    run_extraction_jobs(sprites, &jobs, settings);
+}
+
+/// Extract the cells seen from within `areas` of each visible tilemap
+#[allow(clippy::type_complexity)]
+pub(crate) fn extract_visible_tilemaps<'a, T>(
+    sprites: &mut Vec<ExtractedSprite>,
+    areas: &[[Vec2; 4]],
+    settings: &TilemapExtractionSettings,
+    images: Option<&Assets<Image>>,
+    tilemaps: impl Iterator<
+        Item = (
+            Entity,
+            &'a T,
+            &'a TilemapGeometry,
+            &'a TilemapView,
+            Option<&'a TilemapCellSizes>,
+            &'a GlobalTransform,
+            bool,
+        ),
+    >,
+) where
+    T: ExtractableTilemap,
+{
+    let jobs: Vec<_> = tilemaps
+        .filter(|(.., visible)| *visible)
+        .map(
+            |(entity, tilemap, geometry, view, cell_sizes, transform, _)| {
+                let map_size = [tilemap.width(), tilemap.height()];
+                ExtractionJob {
+                    map_size,
+                    window: visible_window(map_size, geometry, view, cell_sizes, transform, areas),
+                    geometry,
+                    cell_sizes,
+                    transform: *transform,
+                    images,
+                    extract_tile: move |index, transform| {
+                        tilemap.extract_tile(entity, transform, index)
+                    },
+                }
+            },
+        )
+        .collect();
+    run_extraction_jobs(sprites, &jobs, settings);
+}
+
 #[allow(clippy::type_complexity)]
 pub fn extract_atlas_tilemap<T>(
     mut extracted_sprites: ResMut<ExtractedSprites>,
+    settings: Extract<Res<TilemapExtractionSettings>>,
     texture_atlases: Extract<Res<Assets<TextureAtlas>>>,
+    camera_query: Extract<Query<(&Camera, &GlobalTransform)>>,
     tilemap_query: Extract<
         Query<(
             Entity,
             &T,
             &TilemapGeometry,
             &TilemapView,
+            Option<&TilemapCellSizes>,
             &Handle<TextureAtlas>,
             &GlobalTransform,
             &ComputedVisibility,
@@ -106,47 +463,50 @@ pub fn extract_atlas_tilemap<T>(
 ) where
     T: ExtractableAtlasTilemap,
 {
-    for (
-        entity,
-        tilemap,
-        tilemap_geometry,
-        tilemap_view,
-        texture_atlas_handle,
-        global_transform,
-        visibility,
-    ) in tilemap_query.iter()
-    {
-        if let Some(texture_atlas) = texture_atlases.get(texture_atlas_handle) {
-            if !visibility.is_visible() {
-                return;
-            }
-            iter_grid_coords(
-                tilemap.width(),
-                tilemap.height(),
-                tilemap_view,
-                tilemap_geometry,
-                *global_transform,
-            )
-            .for_each(|(index, transform)| {
-                if let Some(extracted_sprite) =
-                    tilemap.extract_tile(entity, transform, texture_atlas, index)
-                {
-                    extracted_sprites.sprites.alloc().init(extracted_sprite);
-                }
-            });
-        }
-    }
+    let areas = camera_areas(camera_query.iter());
+    extract_visible_atlas_tilemaps(
+        &mut extracted_sprites.sprites,
+        &areas,
+        &settings,
+        tilemap_query.iter().filter_map(
+            |(
+                entity,
+                tilemap,
+                geometry,
+                view,
+                cell_sizes,
+                texture_atlas_handle,
+                transform,
+                visibility,
+            )| {
+                Some((
+                    entity,
+                    tilemap,
+                    geometry,
+                    view,
+                    cell_sizes,
+                    texture_atlases.get(texture_atlas_handle)?,
+                    transform,
+                    visibility.is_visible(),
+                ))
+            },
+        ),
+    );
 }
 
 #[allow(clippy::type_complexity)]
 pub fn extract_tilemap<T>(
     mut extracted_sprites: ResMut<ExtractedSprites>,
+    settings: Extract<Res<TilemapExtractionSettings>>,
+    images: Extract<Res<Assets<Image>>>,
+    camera_query: Extract<Query<(&Camera, &GlobalTransform)>>,
     tilemap_query: Extract<
         Query<(
             Entity,
             &T,
             &TilemapGeometry,
             &TilemapView,
+            Option<&TilemapCellSizes>,
             &GlobalTransform,
             &ComputedVisibility,
         )>,
@@ -154,24 +514,87 @@ pub fn extract_tilemap<T>(
 ) where
     T: ExtractableTilemap,
 {
-    for (entity, tilemap, tilemap_geometry, tilemap_view, global_transform, visibility) in
-        tilemap_query.iter()
-    {
-        if !visibility.is_visible() {
-            return;
+    let areas = camera_areas(camera_query.iter());
+    extract_visible_tilemaps(
+        &mut extracted_sprites.sprites,
+        &areas,
+        &settings,
+        Some(&images),
+        tilemap_query.iter().map(
+            |(entity, tilemap, geometry, view, cell_sizes, transform, visibility)| {
+                (
+                    entity,
+                    tilemap,
+                    geometry,
+                    view,
+                    cell_sizes,
+                    transform,
+                    visibility.is_visible(),
+                )
+            },
+        ),
+    );
+}
+
+/// Bounds of the cells drawn by `view` for frustum culling
+pub(crate) fn tilemap_aabb(
+    map_size: [usize; 2],
+    geometry: &TilemapGeometry,
+    view: &TilemapView,
+    cell_sizes: Option<&TilemapCellSizes>,
+) -> Aabb {
+    let bounds = match cell_sizes {
+        Some(cell_sizes) => Some(cell_sizes.bounds(geometry)),
+        None => geometry.view_bounds(map_size, view),
+    };
+    match bounds {
+        Some(bounds) => {
+            let margin = culling_margin(geometry, cell_sizes);
+            Aabb::from_min_max(
+                (bounds.min - margin).extend(0.),
+                (bounds.max + margin).extend(0.),
+            )
         }
-        iter_grid_coords(
-            tilemap.width(),
-            tilemap.height(),
-            tilemap_view,
-            tilemap_geometry,
-            *global_transform,
-        )
-        .for_each(|(index, transform)| {
-            if let Some(extracted_sprite) = tilemap.extract_tile(entity, transform, index) {
-                extracted_sprites.sprites.alloc().init(extracted_sprite);
+        None => Aabb::default(),
+    }
+}
+
+/// Keeps the [`Aabb`] of each tilemap up to date so Bevy can cull tilemaps out of view
+#[allow(clippy::type_complexity)]
+pub fn calculate_tilemap_bounds<T>(
+    mut commands: Commands,
+    mut tilemap_query: Query<
+        (
+            Entity,
+            &T,
+            &TilemapGeometry,
+            &TilemapView,
+            Option<&TilemapCellSizes>,
+            Option<&mut Aabb>,
+        ),
+        Or<(
+            Changed<T>,
+            Changed<TilemapGeometry>,
+            Changed<TilemapView>,
+            Changed<TilemapCellSizes>,
+        )>,
+    >,
+) where
+    T: Component + IndexableGrid,
+{
+    for (entity, tilemap, geometry, view, cell_sizes, aabb) in tilemap_query.iter_mut() {
+        let bounds = tilemap_aabb(
+            [tilemap.width(), tilemap.height()],
+            geometry,
+            view,
+            cell_sizes,
+        );
+        match aabb {
+            Some(mut aabb) => *aabb = bounds,
+            None => {
+                commands.entity(entity).insert(bounds);
             }
-        });
+        }
     }
 }
 
@@ -180,36 +603,566 @@ pub enum TilemapRenderSystem {
     ExtractTiles,
 }
 
-pub(crate) struct TilemapExtractionPlugin;
+/// Adds the system extracting tilemap components `T` drawn with a texture atlas
+pub struct ExtractAtlasTilemapPlugin<T>(PhantomData<fn() -> T>);
 
-impl Plugin for TilemapExtractionPlugin {
+impl<T> Default for ExtractAtlasTilemapPlugin<T> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<T> Plugin for ExtractAtlasTilemapPlugin<T>
+where
+    T: ExtractableAtlasTilemap,
+{
     fn build(&self, app: &mut App) {
+        app.init_resource::<TilemapExtractionSettings>()
+            .add_system_to_stage(
+                CoreStage::PostUpdate,
+                calculate_tilemap_bounds::<T>.label(VisibilitySystems::CalculateBounds),
+            )
+            .add_system_to_stage(
+                CoreStage::PostUpdate,
+                calculate_shared_tilemap_bounds::<T>.label(VisibilitySystems::CalculateBounds),
+            );
         if let Ok(render_app) = app.get_sub_app_mut(RenderApp) {
             render_app
                 .add_system_to_stage(
                     RenderStage::Extract,
-                    extract_atlas_tilemap::<Tilemap<TextureAtlasTile>>
+                    extract_atlas_tilemap::<T>
                         .label(TilemapRenderSystem::ExtractTiles)
                         .after(SpriteSystem::ExtractSprites),
                 )
                 .add_system_to_stage(
                     RenderStage::Extract,
-                    extract_atlas_tilemap::<Tilemap<Option<TextureAtlasTile>>>
+                    extract_shared_atlas_tilemap::<T>
                         .label(TilemapRenderSystem::ExtractTiles)
                         .after(SpriteSystem::ExtractSprites),
-                )
+                );
+        }
+    }
+}
+
+/// Adds the system extracting tilemap components `T` drawn with individual textures
+pub struct ExtractTilemapPlugin<T>(PhantomData<fn() -> T>);
+
+impl<T> Default for ExtractTilemapPlugin<T> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<T> Plugin for ExtractTilemapPlugin<T>
+where
+    T: ExtractableTilemap,
+{
+    fn build(&self, app: &mut App) {
+        app.init_resource::<TilemapExtractionSettings>()
+            .add_system_to_stage(
+                CoreStage::PostUpdate,
+                calculate_tilemap_bounds::<T>.label(VisibilitySystems::CalculateBounds),
+            )
+            .add_system_to_stage(
+                CoreStage::PostUpdate,
+                calculate_shared_tilemap_bounds::<T>.label(VisibilitySystems::CalculateBounds),
+            );
+        if let Ok(render_app) = app.get_sub_app_mut(RenderApp) {
+            render_app
                 .add_system_to_stage(
                     RenderStage::Extract,
-                    extract_tilemap::<Tilemap<SpriteTile>>
+                    extract_tilemap::<T>
                         .label(TilemapRenderSystem::ExtractTiles)
                         .after(SpriteSystem::ExtractSprites),
                 )
                 .add_system_to_stage(
                     RenderStage::Extract,
-                    extract_tilemap::<Tilemap<Option<SpriteTile>>>
+                    extract_shared_tilemap::<T>
                         .label(TilemapRenderSystem::ExtractTiles)
                         .after(SpriteSystem::ExtractSprites),
                 );
         }
     }
 }
+
+pub(crate) struct TilemapExtractionPlugin;
+
+impl Plugin for TilemapExtractionPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugin(ExtractAtlasTilemapPlugin::<Tilemap<TextureAtlasTile>>::default())
+            .add_plugin(ExtractAtlasTilemapPlugin::<Tilemap<Option<TextureAtlasTile>>>::default())
+            .add_plugin(ExtractTilemapPlugin::<Tilemap<SpriteTile>>::default())
+            .add_plugin(ExtractTilemapPlugin::<Tilemap<Option<SpriteTile>>>::default())
+            .add_plugin(ExtractCompactTilemapPlugin::<CompactTile>::default())
+            .add_plugin(ExtractCompactTilemapPlugin::<Option<CompactTile>>::default())
+            .add_plugin(ExtractHashAtlasTilemapPlugin::<TextureAtlasTile>::default())
+            .add_plugin(ExtractHashTilemapPlugin::<SpriteTile>::default());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::math::vec3;
+    use bevy::tasks::TaskPool;
+
+    fn area(min: Vec2, max: Vec2) -> [Vec2; 4] {
+        [min, vec2(max.x, min.y), max, vec2(min.x, max.y)]
+    }
+
+    #[test]
+    fn invisible_tilemap_does_not_hide_others() {
+        let tilemaps: Vec<Tilemap<SpriteTile>> =
+            (0..4).map(|_| Tilemap::from_default(4, 4)).collect();
+        let geometry = TilemapGeometry::default();
+        let view = TilemapView::All;
+        let transform = GlobalTransform::IDENTITY;
+        let areas = [area(-1000. * Vec2::ONE, 1000. * Vec2::ONE)];
+        let mut sprites = vec![];
+        extract_visible_tilemaps(
+            &mut sprites,
+            &areas,
+            &TilemapExtractionSettings::default(),
+            None,
+            tilemaps.iter().enumerate().map(|(i, tilemap)| {
+                (
+                    Entity::from_raw(i as u32),
+                    tilemap,
+                    &geometry,
+                    &view,
+                    None,
+                    &transform,
+                    i != 1,
+                )
+            }),
+        );
+        assert_eq!(sprites.len(), 48);
+        assert!(sprites
+            .iter()
+            .all(|sprite| sprite.entity != Entity::from_raw(1)));
+        assert!(sprites
+            .iter()
+            .any(|sprite| sprite.entity == Entity::from_raw(3)));
+    }
+
+    #[test]
+    fn cells_outside_cameras_are_culled() {
+        let tilemap: Tilemap<SpriteTile> = Tilemap::from_default(100, 100);
+        let geometry = TilemapGeometry::default();
+        let view = TilemapView::All;
+        let near = GlobalTransform::IDENTITY;
+        let far = GlobalTransform::from_translation(vec3(10000., 0., 0.));
+        let settings = TilemapExtractionSettings::default();
+        let tilemaps = [
+            (
+                Entity::from_raw(0),
+                &tilemap,
+                &geometry,
+                &view,
+                None,
+                &near,
+                true,
+            ),
+            (
+                Entity::from_raw(1),
+                &tilemap,
+                &geometry,
+                &view,
+                None,
+                &far,
+                true,
+            ),
+        ];
+
+        let mut sprites = vec![];
+        let areas = [area(-50. * Vec2::ONE, 50. * Vec2::ONE)];
+        extract_visible_tilemaps(&mut sprites, &areas, &settings, None, tilemaps.into_iter());
+        assert_eq!(sprites.len(), 100);
+        assert!(sprites
+            .iter()
+            .all(|sprite| sprite.entity == Entity::from_raw(0)));
+
+        let mut sprites = vec![];
+        let areas = [
+            area(-50. * Vec2::ONE, 50. * Vec2::ONE),
+            area(vec2(9950., -50.), vec2(10050., 50.)),
+        ];
+        extract_visible_tilemaps(&mut sprites, &areas, &settings, None, tilemaps.into_iter());
+        assert!(sprites
+            .iter()
+            .any(|sprite| sprite.entity == Entity::from_raw(1)));
+        assert!(sprites.len() < 100 * 100);
+    }
+
+    #[test]
+    fn culled_sprites_are_positioned_as_unculled() {
+        let tilemap: Tilemap<SpriteTile> = Tilemap::from_default(20, 20);
+        let geometry = TilemapGeometry::default();
+        let view = TilemapView::All;
+        let transform = GlobalTransform::from(Transform {
+            rotation: Quat::from_rotation_z(0.5),
+            scale: vec3(2., 3., 1.),
+            ..Default::default()
+        });
+        let tilemaps = [(
+            Entity::from_raw(0),
+            &tilemap,
+            &geometry,
+            &view,
+            None,
+            &transform,
+            true,
+        )];
+        let settings = TilemapExtractionSettings::default();
+        let mut all = vec![];
+        extract_visible_tilemaps(
+            &mut all,
+            &[area(-1e6 * Vec2::ONE, 1e6 * Vec2::ONE)],
+            &settings,
+            None,
+            tilemaps.into_iter(),
+        );
+        let mut culled = vec![];
+        extract_visible_tilemaps(
+            &mut culled,
+            &[area(vec2(0., 0.), vec2(40., 40.))],
+            &settings,
+            None,
+            tilemaps.into_iter(),
+        );
+        assert!(culled.len() < all.len());
+        for sprite in &culled {
+            assert!(all.iter().any(|other| other
+                .transform
+                .translation()
+                .distance(sprite.transform.translation())
+                < 1e-3));
+        }
+    }
+
+    #[test]
+    fn parallel_extraction_matches_serial() {
+        ComputeTaskPool::init(TaskPool::new);
+        let tilemap: Tilemap<SpriteTile> = Tilemap::from_default(50, 37);
+        let geometry = TilemapGeometry::default();
+        let view = TilemapView::Wrapping {
+            x: 30,
+            y: 20,
+            width: 61,
+            height: 43,
+        };
+        let transform = GlobalTransform::IDENTITY;
+        let tilemaps = [
+            (
+                Entity::from_raw(0),
+                &tilemap,
+                &geometry,
+                &view,
+                None,
+                &transform,
+                true,
+            ),
+            (
+                Entity::from_raw(1),
+                &tilemap,
+                &geometry,
+                &view,
+                None,
+                &transform,
+                true,
+            ),
+        ];
+        let areas = [area(-1e6 * Vec2::ONE, 1e6 * Vec2::ONE)];
+
+        let mut serial = vec![];
+        let settings = TilemapExtractionSettings {
+            parallel_threshold: usize::MAX,
+            ..Default::default()
+        };
+        extract_visible_tilemaps(&mut serial, &areas, &settings, None, tilemaps.into_iter());
+
+        let mut parallel = vec![];
+        let settings = TilemapExtractionSettings {
+            parallel_threshold: 0,
+            cells_per_task: 100,
+        };
+        extract_visible_tilemaps(&mut parallel, &areas, &settings, None, tilemaps.into_iter());
+
+        assert_eq!(serial.len(), 2 * 61 * 43);
+        assert_eq!(serial.len(), parallel.len());
+        let key = |sprite: &ExtractedSprite| {
+            let t = sprite.transform.translation();
+            (sprite.entity, (t.x * 8.) as i64, (t.y * 8.) as i64)
+        };
+        let mut serial: Vec<_> = serial.iter().map(key).collect();
+        let mut parallel: Vec<_> = parallel.iter().map(key).collect();
+        serial.sort();
+        parallel.sort();
+        assert_eq!(serial, parallel);
+    }
+
+    #[test]
+    fn exact_placement_does_not_drift() {
+        let transform = GlobalTransform::from(Transform {
+            translation: vec3(0.37, -5.1, 0.),
+            rotation: Quat::from_rotation_z(0.3),
+            scale: vec3(1.1, 1.1, 1.),
+        });
+        let geometry = TilemapGeometry {
+            placement: CellPlacement::Exact,
+            ..Default::default()
+        };
+        let width = 10_000;
+        let window = TilemapView::All.window([width, 1]);
+        let affine = transform.affine();
+        let origin = geometry.grid_origin([width, 1]).as_dvec2();
+        for (index, cell_transform) in iter_grid_coords(width, 1, window, &geometry, transform) {
+            let local = origin + bevy::math::dvec2(index as f64 * 16., 0.);
+            let expected = affine.translation.as_dvec3().truncate()
+                + local.x * affine.matrix3.x_axis.as_dvec3().truncate()
+                + local.y * affine.matrix3.y_axis.as_dvec3().truncate();
+            let actual = cell_transform.translation().truncate().as_dvec2();
+            assert!(actual.distance(expected) < 0.05, "cell {index}");
+        }
+    }
+
+    #[test]
+    fn snapped_placement_keeps_cells_on_pixels() {
+        let transform =
+            GlobalTransform::from(Transform::from_xyz(0.3, 0.7, 0.).with_scale(vec3(2., 2., 1.)));
+        let geometry = TilemapGeometry {
+            placement: CellPlacement::Snapped { pixel_size: 1. },
+            ..Default::default()
+        };
+        let width = 10_000;
+        let window = TilemapView::All.window([width, 1]);
+        let positions: Vec<Vec3> = iter_grid_coords(width, 1, window, &geometry, transform)
+            .map(|(_, cell_transform)| cell_transform.translation())
+            .collect();
+        for position in &positions {
+            assert_eq!(position.x.fract(), 0.);
+            assert_eq!(position.y.fract(), 0.);
+        }
+        for pair in positions.windows(2) {
+            assert_eq!(pair[1].x - pair[0].x, 32.);
+            assert_eq!(pair[1].y, pair[0].y);
+        }
+    }
+
+    #[test]
+    fn cells_are_sized_individually() {
+        let tilemap: Tilemap<SpriteTile> = Tilemap::from_default(4, 3);
+        let geometry = TilemapGeometry::default();
+        let view = TilemapView::All;
+        let cell_sizes = TilemapCellSizes::new([10., 50., 20., 100.], [5., 5.]);
+        let transform = GlobalTransform::from_xyz(3., 4., 0.);
+        let tilemaps = [(
+            Entity::from_raw(0),
+            &tilemap,
+            &geometry,
+            &view,
+            Some(&cell_sizes),
+            &transform,
+            true,
+        )];
+        let settings = TilemapExtractionSettings::default();
+
+        let mut sprites = vec![];
+        extract_visible_tilemaps(&mut sprites, &[], &settings, None, tilemaps.into_iter());
+        assert_eq!(sprites.len(), 8);
+        for (sprite, [x, y]) in sprites.iter().zip(iter_window_cells(
+            [4, 3],
+            view.window([4, 3]).rows([4, 3], 0, 2),
+        )) {
+            let expected = cell_sizes.cell_to_local(&geometry, [x, y]) + vec2(3., 4.);
+            assert_eq!(sprite.transform.translation().truncate(), expected);
+        }
+
+        let mut sprites = vec![];
+        // culled with a margin of the widest column, only the first column is out of reach
+        let areas = [area(vec2(30., -10.), vec2(40., 10.))];
+        extract_visible_tilemaps(&mut sprites, &areas, &settings, None, tilemaps.into_iter());
+        assert_eq!(sprites.len(), 6);
+
+        let section = TilemapView::Section {
+            x: 0,
+            y: 0,
+            width: 2,
+            height: 3,
+        };
+        let wrapping = TilemapView::Wrapping {
+            x: 3,
+            y: 0,
+            width: 2,
+            height: 2,
+        };
+        for (view, expected) in [(section, 2), (wrapping, 4)] {
+            let tilemaps = [(
+                Entity::from_raw(0),
+                &tilemap,
+                &geometry,
+                &view,
+                Some(&cell_sizes),
+                &transform,
+                true,
+            )];
+            let mut sprites = vec![];
+            extract_visible_tilemaps(&mut sprites, &areas, &settings, None, tilemaps.into_iter());
+            assert_eq!(sprites.len(), expected);
+        }
+    }
+
+    #[test]
+    fn fitted_sprites_follow_quarter_turns() {
+        let cell_transform = GlobalTransform::default();
+        let mut sprite = ExtractedSprite {
+            entity: Entity::from_raw(0),
+            transform: cell_transform,
+            color: Color::WHITE,
+            rect: Some(Rect::from_corners(Vec2::ZERO, vec2(8., 8.))),
+            custom_size: None,
+            image_handle_id: Handle::<Image>::default().id(),
+            flip_x: false,
+            flip_y: false,
+            anchor: Vec2::ZERO,
+        };
+        let cell_size = vec2(32., 16.);
+        let mut turned = sprite;
+        turned.transform = cell_transform.mul_transform(Transform::from_rotation(
+            Quat::from_rotation_z(std::f32::consts::FRAC_PI_2),
+        ));
+        fit_sprite(
+            &mut sprite,
+            TileFit::Stretch,
+            cell_size,
+            &cell_transform,
+            None,
+        );
+        fit_sprite(
+            &mut turned,
+            TileFit::Stretch,
+            cell_size,
+            &cell_transform,
+            None,
+        );
+        assert_eq!(sprite.custom_size, Some(cell_size));
+        assert_eq!(turned.custom_size, Some(vec2(16., 32.)));
+
+        let mut sized = sprite;
+        sized.custom_size = Some(Vec2::ONE);
+        fit_sprite(&mut sized, TileFit::Cover, cell_size, &cell_transform, None);
+        assert_eq!(sized.custom_size, Some(Vec2::ONE));
+    }
+
+    #[test]
+    fn tiles_are_offset_and_scaled_within_their_cells() {
+        let plain = SpriteTile {
+            custom_size: Some(vec2(16., 8.)),
+            ..Default::default()
+        };
+        let moved = SpriteTile {
+            offset: vec2(4., -2.),
+            scale: vec2(0.5, 2.),
+            ..plain.clone()
+        };
+        let tilemaps: Vec<Tilemap<SpriteTile>> = [
+            plain,
+            moved.clone(),
+            SpriteTile {
+                flip_x: true,
+                ..moved.clone()
+            },
+            SpriteTile {
+                flip_y: true,
+                ..moved
+            },
+        ]
+        .into_iter()
+        .map(|tile| Tilemap::from_fn(1, 1, |_, _| tile.clone()))
+        .collect();
+        let geometry = TilemapGeometry::default();
+        let view = TilemapView::All;
+        let transform = GlobalTransform::from(Transform {
+            translation: vec3(5., 7., 0.),
+            scale: vec3(2., 3., 1.),
+            ..Default::default()
+        });
+        let areas = [area(-1000. * Vec2::ONE, 1000. * Vec2::ONE)];
+        let mut sprites = vec![];
+        extract_visible_tilemaps(
+            &mut sprites,
+            &areas,
+            &TilemapExtractionSettings::default(),
+            None,
+            tilemaps.iter().enumerate().map(|(i, tilemap)| {
+                (
+                    Entity::from_raw(i as u32),
+                    tilemap,
+                    &geometry,
+                    &view,
+                    None,
+                    &transform,
+                    true,
+                )
+            }),
+        );
+        sprites.sort_by_key(|sprite| sprite.entity);
+        let placed: Vec<(Vec3, Vec2)> = sprites
+            .iter()
+            .map(|sprite| {
+                let transform = sprite.transform.compute_transform();
+                let size = sprite.custom_size.unwrap() * transform.scale.truncate();
+                (transform.translation, size)
+            })
+            .collect();
+        assert_eq!(placed[0].1, vec2(32., 24.));
+        for &(translation, size) in &placed[1..] {
+            assert!((translation - placed[0].0 - vec3(8., -6., 0.)).length() < 1e-4);
+            assert_eq!(size, vec2(16., 48.));
+        }
+        assert_eq!(
+            sprites
+                .iter()
+                .map(|sprite| [sprite.flip_x, sprite.flip_y])
+                .collect::<Vec<_>>(),
+            vec![[false, false], [false, false], [true, false], [false, true]]
+        );
+    }
+
+    #[test]
+    fn oversized_cells_at_the_edge_are_not_culled() {
+        let tilemap = Tilemap::from_fn(3, 1, |x, _| SpriteTile {
+            scale: Vec2::splat(if x == 2 { 2. } else { 1. }),
+            ..Default::default()
+        });
+        // columns from -96 to -80, -80 to -64 and -64 to 96
+        let cell_sizes = TilemapCellSizes::new([16., 16., 160.], [16.]);
+        let geometry = TilemapGeometry::default();
+        let view = TilemapView::All;
+        let transform = GlobalTransform::IDENTITY;
+        let settings = TilemapExtractionSettings::default();
+        let tilemaps = [(
+            Entity::from_raw(0),
+            &tilemap,
+            &geometry,
+            &view,
+            Some(&cell_sizes),
+            &transform,
+            true,
+        )];
+
+        // the doubled tile of the last column reaches to -144, past the edge of the camera
+        let mut sprites = vec![];
+        let areas = [area(vec2(-300., -50.), vec2(-90., 50.))];
+        extract_visible_tilemaps(&mut sprites, &areas, &settings, None, tilemaps.into_iter());
+        assert_eq!(sprites.len(), 3);
+
+        let mut sprites = vec![];
+        let areas = [area(vec2(-1000., -50.), vec2(-900., 50.))];
+        extract_visible_tilemaps(&mut sprites, &areas, &settings, None, tilemaps.into_iter());
+        assert!(sprites.is_empty());
+
+        let aabb = tilemap_aabb([3, 1], &geometry, &view, Some(&cell_sizes));
+        assert_eq!(aabb.half_extents.x, 96. + 160.);
+    }
+}
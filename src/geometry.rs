@@ -1,11 +1,82 @@
+use bevy::math::vec2;
+use bevy::math::Vec3A;
 use bevy::prelude::*;
 use bevy::sprite::Anchor;
 
+/// How the position of each cell is computed during extraction
+#[derive(Clone, Copy, Debug, Default, PartialEq, Reflect)]
+pub enum CellPlacement {
+    /// Step from each cell to the next.
+    /// Fastest, but rounding errors build up along the rows of large or transformed tilemaps.
+    #[default]
+    Accumulated,
+    /// Compute each cell's position from its coordinates
+    Exact,
+    /// Compute each cell's position from its coordinates and round it to the nearest
+    /// multiple of `pixel_size`, the size in world units of a screen pixel.
+    /// For cameras with integer scaling this keeps the edges of tiles on pixel boundaries.
+    Snapped { pixel_size: f32 },
+}
+
+impl CellPlacement {
+    /// Round a cell's world space translation as required by the placement
+    #[inline]
+    pub fn snap(self, translation: Vec3A) -> Vec3A {
+        match self {
+            CellPlacement::Snapped { pixel_size } if 0. < pixel_size => Vec3A::new(
+                (translation.x / pixel_size).round() * pixel_size,
+                (translation.y / pixel_size).round() * pixel_size,
+                translation.z,
+            ),
+            _ => translation,
+        }
+    }
+}
+
+/// How tiles without a `custom_size` are sized to their cells during extraction
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Reflect)]
+pub enum TileFit {
+    /// Draw each image at its own size
+    #[default]
+    Native,
+    /// Stretch each image to the size of its cell
+    Stretch,
+    /// Scale each image to the largest size that fits within its cell, keeping its aspect ratio
+    Contain,
+    /// Scale each image to the smallest size that covers its cell, keeping its aspect ratio,
+    /// and crop the parts outside of the cell
+    Cover,
+}
+
+impl TileFit {
+    /// The part of the image `source` to draw and the size to draw it at in a cell of size `cell_size`,
+    /// or `None` if the image is drawn unchanged
+    pub fn fit(self, source: Rect, cell_size: Vec2) -> Option<(Rect, Vec2)> {
+        let image_size = source.size();
+        if self == TileFit::Native || image_size.cmple(Vec2::ZERO).any() {
+            return None;
+        }
+        let cell_size = cell_size.abs();
+        match self {
+            TileFit::Native => None,
+            TileFit::Stretch => Some((source, cell_size)),
+            TileFit::Contain => Some((source, image_size * (cell_size / image_size).min_element())),
+            TileFit::Cover => {
+                let visible = cell_size * (image_size / cell_size).min_element();
+                Some((
+                    Rect::from_center_size(source.center(), visible.min(image_size)),
+                    cell_size,
+                ))
+            }
+        }
+    }
+}
+
 #[derive(Clone, Component, Debug, Reflect)]
 #[reflect(Component)]
 pub struct TilemapGeometry {
     /// size of each grid cell
-    /// textures are not strectched or shrunk to fill cells
+    /// textures are only stretched or shrunk to fill cells as set by `fit`
     pub tile_size: Vec2,
     /// if false draw rows in reverse order (index increases from right to left)
     pub reverse_rows: bool,
@@ -13,6 +84,14 @@ pub struct TilemapGeometry {
     pub reverse_columns: bool,
     /// how the grid is positioned relative to its transform
     pub anchor: Anchor,
+    /// how cell positions are computed, set to avoid seams between tiles
+    pub placement: CellPlacement,
+    /// gap between adjacent cells along each axis
+    pub spacing: Vec2,
+    /// space around the outside of the grid, included when positioning the grid by its anchor
+    pub margin: Vec2,
+    /// how tile images are sized to their cells
+    pub fit: TileFit,
 }
 
 impl Default for TilemapGeometry {
@@ -22,7 +101,92 @@ impl Default for TilemapGeometry {
             anchor: Anchor::Center,
             reverse_rows: false,
             reverse_columns: false,
+            placement: CellPlacement::default(),
+            spacing: Vec2::ZERO,
+            margin: Vec2::ZERO,
+            fit: TileFit::default(),
+        }
+    }
+}
+
+impl TilemapGeometry {
+    /// Offset between the centres of adjacent cells along each axis
+    #[inline]
+    pub fn cell_step(&self) -> Vec2 {
+        let pitch = self.tile_size + self.spacing;
+        vec2(
+            if self.reverse_rows { -pitch.x } else { pitch.x },
+            if self.reverse_columns {
+                -pitch.y
+            } else {
+                pitch.y
+            },
+        )
+    }
+
+    /// Size of a grid with dimensions `grid_size` in cells, including its margin
+    #[inline]
+    pub fn grid_extent(&self, grid_size: [usize; 2]) -> Vec2 {
+        let grid_dimensions = vec2(grid_size[0] as f32, grid_size[1] as f32);
+        let cells = grid_dimensions * (self.tile_size + self.spacing) - self.spacing;
+        cells.max(Vec2::ZERO) + 2. * self.margin
+    }
+
+    /// Position of the centre of cell `[0, 0]` relative to the transform
+    /// for a grid with dimensions `grid_size` in cells
+    #[inline]
+    pub fn grid_origin(&self, grid_size: [usize; 2]) -> Vec2 {
+        let extent = self.grid_extent(grid_size);
+        let last = vec2(
+            grid_size[0].saturating_sub(1) as f32,
+            grid_size[1].saturating_sub(1) as f32,
+        ) * (self.tile_size + self.spacing);
+        0.5 * self.tile_size + self.margin - (0.5 + self.anchor.as_vec()) * extent
+            + vec2(
+                if self.reverse_rows { last.x } else { 0.0 },
+                if self.reverse_columns { last.y } else { 0.0 },
+            )
+    }
+
+    /// Convert cell coordinates to a position relative to the transform.
+    /// Cell centres are at whole numbered coordinates.
+    #[inline]
+    pub fn cell_to_local(&self, grid_size: [usize; 2], cell: Vec2) -> Vec2 {
+        self.grid_origin(grid_size) + cell * self.cell_step()
+    }
+
+    /// Convert a position relative to the transform to cell coordinates.
+    /// Cell centres are at whole numbered coordinates.
+    #[inline]
+    pub fn local_to_cell(&self, grid_size: [usize; 2], point: Vec2) -> Vec2 {
+        (point - self.grid_origin(grid_size)) / self.cell_step()
+    }
+
+    /// True if the point at cell coordinates `cell` is covered by the cell nearest to it,
+    /// false if it falls in the spacing between cells
+    #[inline]
+    pub fn is_within_cell(&self, cell: Vec2) -> bool {
+        let distance = (cell - cell.round()).abs() * (self.tile_size + self.spacing).abs();
+        distance.cmple(0.5 * self.tile_size.abs()).all()
+    }
+
+    /// Bounds relative to the transform of the cells drawn by `view`,
+    /// or `None` if no cells are drawn
+    pub fn view_bounds(&self, map_size: [usize; 2], view: &TilemapView) -> Option<Rect> {
+        let window = view.window(map_size);
+        if window.width == 0 || window.height == 0 {
+            return None;
         }
+        let last = vec2(window.width as f32, window.height as f32) - 1.;
+        let centres = Rect::from_corners(
+            self.cell_to_local(window.layout, window.origin),
+            self.cell_to_local(window.layout, window.origin + last),
+        );
+        let border = 0.5 * self.tile_size.abs() + self.margin;
+        Some(Rect {
+            min: centres.min - border,
+            max: centres.max + border,
+        })
     }
 }
 
@@ -39,6 +203,102 @@ pub enum TilemapView {
         width: usize,
         height: usize,
     },
+    /// Draw a rectangular subsection of the tilemap that wraps around its edges.
+    /// Cells past the right or top edge continue from the left or bottom,
+    /// as if the tilemap was repeated endlessly.
+    Wrapping {
+        x: usize,
+        y: usize,
+        width: usize,
+        height: usize,
+    },
+    /// Draw a `width` by `height` window onto the tilemap scrolled to the cell position `x`, `y`.
+    /// The window is positioned by the geometry as if it were a tilemap of its own,
+    /// and its contents are offset by the fractional part of the scroll position.
+    /// Partly visible cells at the edges of the window are drawn whole.
+    Scroll {
+        x: f32,
+        y: f32,
+        width: usize,
+        height: usize,
+        /// continue from the opposite edge past the edges of the tilemap
+        wrap: bool,
+    },
+}
+
+/// The cells of a tilemap drawn by a [`TilemapView`]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ViewWindow {
+    /// column of the first cell drawn
+    pub x: usize,
+    /// row of the first cell drawn
+    pub y: usize,
+    /// number of columns drawn
+    pub width: usize,
+    /// number of rows drawn
+    pub height: usize,
+    /// position of the first cell drawn in the grid, in cells
+    pub origin: Vec2,
+    /// dimensions in cells of the grid positioned by the geometry
+    pub layout: [usize; 2],
+}
+
+/// Returns the index of the first cell and the number of cells of a window along one axis
+/// that overlap the range `min..max` in grid coordinates
+#[inline]
+fn restrict_axis(origin: f32, length: usize, min: f32, max: f32) -> (usize, usize) {
+    if !(min.is_finite() && max.is_finite()) {
+        return (0, length);
+    }
+    let first = (min - origin - 0.5).ceil().max(0.);
+    let last = (max - origin + 0.5).floor().min(length as f32 - 1.);
+    if last < first {
+        (0, 0)
+    } else {
+        (first as usize, (last - first) as usize + 1)
+    }
+}
+
+impl ViewWindow {
+    #[inline]
+    fn empty(layout: [usize; 2]) -> Self {
+        Self {
+            x: 0,
+            y: 0,
+            width: 0,
+            height: 0,
+            origin: Vec2::ZERO,
+            layout,
+        }
+    }
+
+    /// Up to `count` rows of the window starting from its row `first`
+    pub fn rows(&self, map_size: [usize; 2], first: usize, count: usize) -> ViewWindow {
+        let first = first.min(self.height);
+        ViewWindow {
+            y: (self.y + first) % map_size[1].max(1),
+            height: count.min(self.height - first),
+            origin: self.origin + vec2(0., first as f32),
+            ..*self
+        }
+    }
+
+    /// The cells of the window overlapping the rectangle from `min` to `max` in grid coordinates
+    pub fn restrict(&self, map_size: [usize; 2], min: Vec2, max: Vec2) -> ViewWindow {
+        let (skip_x, width) = restrict_axis(self.origin.x, self.width, min.x, max.x);
+        let (skip_y, height) = restrict_axis(self.origin.y, self.height, min.y, max.y);
+        if width == 0 || height == 0 {
+            return ViewWindow::empty(self.layout);
+        }
+        ViewWindow {
+            x: (self.x + skip_x) % map_size[0],
+            y: (self.y + skip_y) % map_size[1],
+            width,
+            height,
+            origin: self.origin + vec2(skip_x as f32, skip_y as f32),
+            layout: self.layout,
+        }
+    }
 }
 
 #[inline]
@@ -46,22 +306,97 @@ fn clip_axis(space: usize, min: usize, length: usize) -> usize {
     length.min(space.saturating_sub(min))
 }
 
+/// Returns the first cell, number of cells and origin of a scrolled window along one axis
+#[inline]
+fn scroll_axis(space: usize, position: f32, length: usize, wrap: bool) -> (usize, usize, f32) {
+    let start = position.floor();
+    let fraction = position - start;
+    let count = if 0. < fraction { length + 1 } else { length };
+    let start = start as i64;
+    if wrap {
+        (start.rem_euclid(space as i64) as usize, count, -fraction)
+    } else {
+        let first = start.clamp(0, space as i64);
+        let end = (start + count as i64).clamp(first, space as i64);
+        (
+            first as usize,
+            (end - first) as usize,
+            (first - start) as f32 - fraction,
+        )
+    }
+}
+
 impl TilemapView {
     #[inline]
     pub fn clip(&self, map_size: [usize; 2]) -> [usize; 4] {
+        let window = self.window(map_size);
+        [window.x, window.y, window.width, window.height]
+    }
+
+    /// The cells drawn for a tilemap with dimensions `map_size`
+    #[inline]
+    pub fn window(&self, map_size: [usize; 2]) -> ViewWindow {
         match *self {
-            TilemapView::All => [0, 0, map_size[0], map_size[1]],
+            TilemapView::All => ViewWindow {
+                x: 0,
+                y: 0,
+                width: map_size[0],
+                height: map_size[1],
+                origin: Vec2::ZERO,
+                layout: map_size,
+            },
             TilemapView::Section {
                 x,
                 y,
                 width,
                 height,
-            } => [
+            } => ViewWindow {
                 x,
                 y,
-                clip_axis(map_size[0], x, width),
-                clip_axis(map_size[1], y, height),
-            ],
+                width: clip_axis(map_size[0], x, width),
+                height: clip_axis(map_size[1], y, height),
+                origin: vec2(x as f32, y as f32),
+                layout: map_size,
+            },
+            TilemapView::Wrapping {
+                x,
+                y,
+                width,
+                height,
+            } => {
+                if map_size[0] == 0 || map_size[1] == 0 {
+                    return ViewWindow::empty(map_size);
+                }
+                ViewWindow {
+                    x: x % map_size[0],
+                    y: y % map_size[1],
+                    width,
+                    height,
+                    origin: vec2(x as f32, y as f32),
+                    layout: map_size,
+                }
+            }
+            TilemapView::Scroll {
+                x,
+                y,
+                width,
+                height,
+                wrap,
+            } => {
+                if wrap && (map_size[0] == 0 || map_size[1] == 0) {
+                    return ViewWindow::empty([width, height]);
+                }
+                let (x, width_drawn, origin_x) = scroll_axis(map_size[0], x, width, wrap);
+                let (y, height_drawn, origin_y) = scroll_axis(map_size[1], y, height, wrap);
+                ViewWindow {
+                    x,
+                    y,
+                    width: width_drawn,
+                    height: height_drawn,
+                    origin: vec2(origin_x, origin_y),
+                    layout: [width, height],
+                }
+            }
         }
     }
 }
@@ -141,4 +476,208 @@ mod tests {
         };
         assert_eq!(clip(view), [15, 10]);
     }
+
+    #[test]
+    fn wrapping_window() {
+        let s = [15, 20];
+
+        let view = TilemapView::Wrapping {
+            x: 3,
+            y: 4,
+            width: 2,
+            height: 5,
+        };
+        assert_eq!(view.clip(s), [3, 4, 2, 5]);
+
+        let view = TilemapView::Wrapping {
+            x: 10,
+            y: 18,
+            width: 30,
+            height: 7,
+        };
+        assert_eq!(view.clip(s), [10, 18, 30, 7]);
+
+        let view = TilemapView::Wrapping {
+            x: 47,
+            y: 60,
+            width: 5,
+            height: 5,
+        };
+        let window = view.window(s);
+        assert_eq!([window.x, window.y], [2, 0]);
+        assert_eq!(window.origin, vec2(47., 60.));
+
+        assert_eq!(view.clip([0, 20]), [0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn scroll_window() {
+        let s = [15, 20];
+
+        let view = TilemapView::Scroll {
+            x: 3.,
+            y: 4.,
+            width: 5,
+            height: 6,
+            wrap: false,
+        };
+        let window = view.window(s);
+        assert_eq!(view.clip(s), [3, 4, 5, 6]);
+        assert_eq!(window.origin, Vec2::ZERO);
+        assert_eq!(window.layout, [5, 6]);
+
+        let view = TilemapView::Scroll {
+            x: 3.25,
+            y: 4.5,
+            width: 5,
+            height: 6,
+            wrap: false,
+        };
+        let window = view.window(s);
+        assert_eq!(view.clip(s), [3, 4, 6, 7]);
+        assert_eq!(window.origin, vec2(-0.25, -0.5));
+
+        let view = TilemapView::Scroll {
+            x: -1.5,
+            y: 17.5,
+            width: 5,
+            height: 6,
+            wrap: false,
+        };
+        let window = view.window(s);
+        assert_eq!(view.clip(s), [0, 17, 4, 3]);
+        assert_eq!(window.origin, vec2(1.5, -0.5));
+
+        let view = TilemapView::Scroll {
+            x: -1.5,
+            y: 37.,
+            width: 5,
+            height: 6,
+            wrap: true,
+        };
+        let window = view.window(s);
+        assert_eq!(view.clip(s), [13, 17, 6, 6]);
+        assert_eq!(window.origin, vec2(-0.5, 0.));
+    }
+
+    #[test]
+    fn restrict_window() {
+        let s = [15, 20];
+        let window = TilemapView::All.window(s);
+
+        let restricted = window.restrict(s, vec2(2.2, 3.5), vec2(6.4, 4.4));
+        assert_eq!(
+            [
+                restricted.x,
+                restricted.y,
+                restricted.width,
+                restricted.height
+            ],
+            [2, 3, 5, 2]
+        );
+        assert_eq!(restricted.origin, vec2(2., 3.));
+
+        let restricted = window.restrict(s, vec2(-100., -100.), vec2(100., 100.));
+        assert_eq!(restricted, window);
+
+        let restricted = window.restrict(s, vec2(20., 0.), vec2(30., 10.));
+        assert_eq!(restricted.width * restricted.height, 0);
+
+        let window = TilemapView::Wrapping {
+            x: 10,
+            y: 0,
+            width: 10,
+            height: 1,
+        }
+        .window(s);
+        let restricted = window.restrict(s, vec2(14.6, 0.), vec2(16., 0.));
+        assert_eq!([restricted.x, restricted.width], [0, 2]);
+        assert_eq!(restricted.origin, vec2(15., 0.));
+    }
+
+    #[test]
+    fn cell_local_round_trip() {
+        let grid_size = [7, 5];
+        for (reverse_rows, reverse_columns, anchor) in [
+            (false, false, Anchor::BottomLeft),
+            (true, false, Anchor::Center),
+            (false, true, Anchor::TopRight),
+            (true, true, Anchor::Custom(vec2(0.3, -0.1))),
+        ] {
+            let geometry = TilemapGeometry {
+                tile_size: vec2(16., 9.),
+                reverse_rows,
+                reverse_columns,
+                anchor,
+                ..Default::default()
+            };
+            for cell in [vec2(0., 0.), vec2(6., 4.), vec2(2.5, 3.25)] {
+                let local = geometry.cell_to_local(grid_size, cell);
+                let back = geometry.local_to_cell(grid_size, local);
+                assert!((back - cell).abs().max_element() < 1e-4);
+            }
+        }
+
+        let geometry = TilemapGeometry {
+            anchor: Anchor::BottomLeft,
+            ..Default::default()
+        };
+        assert_eq!(geometry.cell_to_local(grid_size, Vec2::ZERO), vec2(8., 8.));
+    }
+
+    #[test]
+    fn spacing_and_margin() {
+        let geometry = TilemapGeometry {
+            tile_size: 10. * Vec2::ONE,
+            spacing: 2. * Vec2::ONE,
+            margin: 5. * Vec2::ONE,
+            anchor: Anchor::BottomLeft,
+            ..Default::default()
+        };
+        let grid_size = [3, 2];
+        assert_eq!(geometry.grid_extent(grid_size), vec2(44., 32.));
+        assert_eq!(
+            geometry.cell_to_local(grid_size, Vec2::ZERO),
+            vec2(10., 10.)
+        );
+        assert_eq!(
+            geometry.cell_to_local(grid_size, vec2(1., 0.)),
+            vec2(22., 10.)
+        );
+        assert_eq!(
+            geometry.view_bounds(grid_size, &TilemapView::All),
+            Some(Rect::from_corners(Vec2::ZERO, vec2(44., 32.)))
+        );
+        let cell = |x: f32| geometry.local_to_cell(grid_size, vec2(x, 10.));
+        assert!(geometry.is_within_cell(cell(14.)));
+        assert!(!geometry.is_within_cell(cell(16.)));
+        assert!(geometry.is_within_cell(cell(17.5)));
+
+        let centred = TilemapGeometry {
+            anchor: Anchor::Center,
+            reverse_rows: true,
+            ..geometry
+        };
+        let a = centred.cell_to_local(grid_size, Vec2::ZERO);
+        let b = centred.cell_to_local(grid_size, vec2(2., 1.));
+        assert_eq!(a + b, Vec2::ZERO);
+        assert_eq!(a.x, 12.);
+    }
+
+    #[test]
+    fn tile_fit() {
+        let source = Rect::from_corners(vec2(10., 0.), vec2(50., 20.));
+        let cell = vec2(16., 16.);
+        assert_eq!(TileFit::Native.fit(source, cell), None);
+        assert_eq!(TileFit::Stretch.fit(source, cell), Some((source, cell)));
+        assert_eq!(
+            TileFit::Contain.fit(source, cell),
+            Some((source, vec2(16., 8.)))
+        );
+        assert_eq!(
+            TileFit::Cover.fit(source, cell),
+            Some((Rect::from_corners(vec2(20., 0.), vec2(40., 20.)), cell))
+        );
+        assert_eq!(TileFit::Cover.fit(Rect::default(), cell), None);
+    }
 }
@@ -0,0 +1,535 @@
+use crate::extractable_tilemaps::AsAtlasTile;
+use crate::extractable_tilemaps::AsSpriteTile;
+use crate::extraction::camera_areas;
+use crate::extraction::fit_sprite;
+use crate::extraction::TilemapRenderSystem;
+use crate::extraction::CULLING_MARGIN;
+use crate::geometry::TilemapGeometry;
+use crate::tile::SpriteTile;
+use crate::tile::TextureAtlasTile;
+use crate::tile::Tileable;
+use crate::tilemap::Tilemap;
+use bevy::math::ivec2;
+use bevy::math::vec2;
+use bevy::prelude::*;
+use bevy::render::primitives::Aabb;
+use bevy::render::view::VisibilitySystems;
+use bevy::render::Extract;
+use bevy::render::RenderApp;
+use bevy::render::RenderStage;
+use bevy::sprite::ExtractedSprites;
+use bevy::sprite::SpriteSystem;
+use bevy::utils::HashMap;
+use copyless::VecHelper;
+use std::marker::PhantomData;
+
+/// A sparse tilemap without fixed dimensions, storing only its occupied cells.
+///
+/// Cells have signed coordinates and the geometry's anchor positions cell `[0, 0]`
+/// as if it were a tilemap with a single cell.
+#[derive(Clone, Component, Debug)]
+pub struct HashTilemap<T>
+where
+    T: Tileable,
+{
+    tiles: HashMap<IVec2, T>,
+}
+
+impl<T> Default for HashTilemap<T>
+where
+    T: Tileable,
+{
+    fn default() -> Self {
+        Self {
+            tiles: HashMap::default(),
+        }
+    }
+}
+
+impl<T> HashTilemap<T>
+where
+    T: Tileable,
+{
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of occupied cells
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.tiles.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.tiles.is_empty()
+    }
+
+    #[inline]
+    pub fn get(&self, cell: IVec2) -> Option<&T> {
+        self.tiles.get(&cell)
+    }
+
+    #[inline]
+    pub fn get_mut(&mut self, cell: IVec2) -> Option<&mut T> {
+        self.tiles.get_mut(&cell)
+    }
+
+    /// Set the tile at `cell`, returning the tile it replaced
+    #[inline]
+    pub fn insert(&mut self, cell: IVec2, tile: T) -> Option<T> {
+        self.tiles.insert(cell, tile)
+    }
+
+    /// Empty the cell at `cell`, returning its tile
+    #[inline]
+    pub fn remove(&mut self, cell: IVec2) -> Option<T> {
+        self.tiles.remove(&cell)
+    }
+
+    /// Empty every cell
+    pub fn clear(&mut self) {
+        self.tiles.clear();
+    }
+
+    /// The occupied cells, in no particular order
+    pub fn iter(&self) -> impl Iterator<Item = (IVec2, &T)> {
+        self.tiles.iter().map(|(cell, tile)| (*cell, tile))
+    }
+
+    /// The occupied cells, in no particular order
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (IVec2, &mut T)> {
+        self.tiles.iter_mut().map(|(cell, tile)| (*cell, tile))
+    }
+
+    /// The minimum and maximum coordinates of the occupied cells,
+    /// or `None` if every cell is empty
+    pub fn bounds(&self) -> Option<[IVec2; 2]> {
+        let mut cells = self.tiles.keys();
+        let first = *cells.next()?;
+        Some(cells.fold([first, first], |[min, max], &cell| {
+            [min.min(cell), max.max(cell)]
+        }))
+    }
+
+    /// Copy every cell of `tilemap`, with its cell `[0, 0]` placed at `origin`
+    pub fn from_tilemap(tilemap: &Tilemap<T>, origin: IVec2) -> Self {
+        Self {
+            tiles: tilemap
+                .indexed_iter()
+                .map(|(x, y, tile)| (origin + ivec2(x as i32, y as i32), tile.clone()))
+                .collect(),
+        }
+    }
+
+    /// Copy the occupied cells of `tilemap`, with its cell `[0, 0]` placed at `origin`
+    pub fn from_sparse_tilemap(tilemap: &Tilemap<Option<T>>, origin: IVec2) -> Self {
+        Self {
+            tiles: tilemap
+                .indexed_iter()
+                .filter_map(|(x, y, tile)| {
+                    Some((origin + ivec2(x as i32, y as i32), tile.clone()?))
+                })
+                .collect(),
+        }
+    }
+
+    /// Copy the cells within the bounds into a dense tilemap.
+    /// Returns the coordinates of the tilemap's cell `[0, 0]` and the tilemap,
+    /// or `None` if every cell is empty or the bounds cover more than `max_cells` cells.
+    pub fn to_tilemap(&self, max_cells: usize) -> Option<(IVec2, Tilemap<Option<T>>)> {
+        let [min, max] = self.bounds()?;
+        let width = usize::try_from(max.x as i64 - min.x as i64 + 1).ok()?;
+        let height = usize::try_from(max.y as i64 - min.y as i64 + 1).ok()?;
+        if max_cells < width.checked_mul(height)? {
+            return None;
+        }
+        let tilemap = Tilemap::from_fn(width, height, |x, y| {
+            self.get(min + ivec2(x as i32, y as i32)).cloned()
+        });
+        Some((min, tilemap))
+    }
+}
+
+impl<T> std::ops::Index<IVec2> for HashTilemap<T>
+where
+    T: Tileable,
+{
+    type Output = T;
+
+    #[inline]
+    fn index(&self, cell: IVec2) -> &Self::Output {
+        &self.tiles[&cell]
+    }
+}
+
+impl<T> FromIterator<(IVec2, T)> for HashTilemap<T>
+where
+    T: Tileable,
+{
+    fn from_iter<I: IntoIterator<Item = (IVec2, T)>>(iter: I) -> Self {
+        Self {
+            tiles: iter.into_iter().collect(),
+        }
+    }
+}
+
+impl<T> IntoIterator for HashTilemap<T>
+where
+    T: Tileable,
+{
+    type Item = (IVec2, T);
+
+    type IntoIter = bevy::utils::hashbrown::hash_map::IntoIter<IVec2, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.tiles.into_iter()
+    }
+}
+
+#[derive(Bundle, Default)]
+pub struct HashAtlasTilemapBundle {
+    pub tilemap: HashTilemap<TextureAtlasTile>,
+    pub geometry: TilemapGeometry,
+    pub texture_atlas: Handle<TextureAtlas>,
+    pub transform: Transform,
+    pub global_transform: GlobalTransform,
+    pub visibility: Visibility,
+    pub computed_visibility: ComputedVisibility,
+}
+
+#[derive(Bundle, Default)]
+pub struct HashSpriteTilemapBundle {
+    pub tilemap: HashTilemap<SpriteTile>,
+    pub geometry: TilemapGeometry,
+    pub transform: Transform,
+    pub global_transform: GlobalTransform,
+    pub visibility: Visibility,
+    pub computed_visibility: ComputedVisibility,
+}
+
+/// The occupied cells of `tilemap` that may be seen from within `areas`,
+/// with the transform of each cell
+pub(crate) fn visible_cells<'a, T>(
+    tilemap: &'a HashTilemap<T>,
+    geometry: &'a TilemapGeometry,
+    transform: &'a GlobalTransform,
+    areas: &[[Vec2; 4]],
+) -> Box<dyn Iterator<Item = (&'a T, GlobalTransform)> + 'a>
+where
+    T: Tileable,
+{
+    let world_to_local = transform.affine().inverse();
+    let z = transform.translation().z;
+    let mut min = Vec2::splat(f32::INFINITY);
+    let mut max = Vec2::splat(f32::NEG_INFINITY);
+    for corner in areas.iter().flatten() {
+        let local = world_to_local.transform_point3(corner.extend(z)).truncate();
+        let cell = geometry.local_to_cell([1, 1], local);
+        min = min.min(cell);
+        max = max.max(cell);
+    }
+    let cell_transform = move |cell: IVec2| {
+        let local = geometry.cell_to_local([1, 1], cell.as_vec2());
+        let mut cell_transform = *transform;
+        *cell_transform.translation_mut() = transform.transform_point(local.extend(0.)).into();
+        cell_transform
+    };
+    if !(min.is_finite() && max.is_finite()) {
+        return Box::new(
+            tilemap
+                .iter()
+                .map(move |(cell, tile)| (tile, cell_transform(cell))),
+        );
+    }
+    // float to int casts saturate, so far off views are clamped to the range of cell coordinates
+    let min = (min - CULLING_MARGIN).round().as_ivec2();
+    let max = (max + CULLING_MARGIN).round().as_ivec2();
+    if max.x < min.x || max.y < min.y {
+        return Box::new(std::iter::empty());
+    }
+    let area = max.as_dvec2() - min.as_dvec2() + 1.;
+    if area.x * area.y < tilemap.len() as f64 {
+        Box::new(
+            (min.y..=max.y)
+                .flat_map(move |y| (min.x..=max.x).map(move |x| ivec2(x, y)))
+                .filter_map(move |cell| Some((tilemap.get(cell)?, cell_transform(cell)))),
+        )
+    } else {
+        Box::new(
+            tilemap
+                .iter()
+                .filter(move |(cell, _)| cell.cmpge(min).all() && cell.cmple(max).all())
+                .map(move |(cell, tile)| (tile, cell_transform(cell))),
+        )
+    }
+}
+
+#[allow(clippy::type_complexity)]
+pub fn extract_hash_atlas_tilemap<T>(
+    mut extracted_sprites: ResMut<ExtractedSprites>,
+    texture_atlases: Extract<Res<Assets<TextureAtlas>>>,
+    camera_query: Extract<Query<(&Camera, &GlobalTransform)>>,
+    tilemap_query: Extract<
+        Query<(
+            Entity,
+            &HashTilemap<T>,
+            &TilemapGeometry,
+            &Handle<TextureAtlas>,
+            &GlobalTransform,
+            &ComputedVisibility,
+        )>,
+    >,
+) where
+    T: AsAtlasTile,
+{
+    let areas = camera_areas(camera_query.iter());
+    for (entity, tilemap, geometry, texture_atlas_handle, transform, visibility) in
+        tilemap_query.iter()
+    {
+        if !visibility.is_visible() {
+            continue;
+        }
+        let texture_atlas = match texture_atlases.get(texture_atlas_handle) {
+            Some(texture_atlas) => texture_atlas,
+            None => continue,
+        };
+        for (tile, transform) in visible_cells(tilemap, geometry, transform, &areas) {
+            if let Some(tile) = tile.as_atlas_tile() {
+                let mut extracted_sprite = tile.extract(entity, transform, texture_atlas);
+                fit_sprite(
+                    &mut extracted_sprite,
+                    geometry.fit,
+                    geometry.tile_size,
+                    &transform,
+                    None,
+                );
+                extracted_sprites.sprites.alloc().init(extracted_sprite);
+            }
+        }
+    }
+}
+
+#[allow(clippy::type_complexity)]
+pub fn extract_hash_tilemap<T>(
+    mut extracted_sprites: ResMut<ExtractedSprites>,
+    images: Extract<Res<Assets<Image>>>,
+    camera_query: Extract<Query<(&Camera, &GlobalTransform)>>,
+    tilemap_query: Extract<
+        Query<(
+            Entity,
+            &HashTilemap<T>,
+            &TilemapGeometry,
+            &GlobalTransform,
+            &ComputedVisibility,
+        )>,
+    >,
+) where
+    T: AsSpriteTile,
+{
+    let areas = camera_areas(camera_query.iter());
+    for (entity, tilemap, geometry, transform, visibility) in tilemap_query.iter() {
+        if !visibility.is_visible() {
+            continue;
+        }
+        for (tile, transform) in visible_cells(tilemap, geometry, transform, &areas) {
+            if let Some(tile) = tile.as_sprite_tile() {
+                let mut extracted_sprite = tile.extract(entity, transform);
+                fit_sprite(
+                    &mut extracted_sprite,
+                    geometry.fit,
+                    geometry.tile_size,
+                    &transform,
+                    Some(&images),
+                );
+                extracted_sprites.sprites.alloc().init(extracted_sprite);
+            }
+        }
+    }
+}
+
+/// Keeps the [`Aabb`] of each hash tilemap up to date so Bevy can cull tilemaps out of view
+#[allow(clippy::type_complexity)]
+pub fn calculate_hash_tilemap_bounds<T>(
+    mut commands: Commands,
+    mut tilemap_query: Query<
+        (Entity, &HashTilemap<T>, &TilemapGeometry, Option<&mut Aabb>),
+        Or<(Changed<HashTilemap<T>>, Changed<TilemapGeometry>)>,
+    >,
+) where
+    T: Tileable,
+{
+    for (entity, tilemap, geometry, aabb) in tilemap_query.iter_mut() {
+        let bounds = match tilemap.bounds() {
+            Some([min, max]) => {
+                let margin = vec2(CULLING_MARGIN, CULLING_MARGIN) + 0.5;
+                let a = geometry.cell_to_local([1, 1], min.as_vec2() - margin);
+                let b = geometry.cell_to_local([1, 1], max.as_vec2() + margin);
+                Aabb::from_min_max(a.min(b).extend(0.), a.max(b).extend(0.))
+            }
+            None => Aabb::default(),
+        };
+        match aabb {
+            Some(mut aabb) => *aabb = bounds,
+            None => {
+                commands.entity(entity).insert(bounds);
+            }
+        }
+    }
+}
+
+/// Adds the system extracting `HashTilemap<T>`s drawn with a texture atlas
+pub struct ExtractHashAtlasTilemapPlugin<T>(PhantomData<fn() -> T>);
+
+impl<T> Default for ExtractHashAtlasTilemapPlugin<T> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<T> Plugin for ExtractHashAtlasTilemapPlugin<T>
+where
+    T: AsAtlasTile,
+{
+    fn build(&self, app: &mut App) {
+        app.add_system_to_stage(
+            CoreStage::PostUpdate,
+            calculate_hash_tilemap_bounds::<T>.label(VisibilitySystems::CalculateBounds),
+        );
+        if let Ok(render_app) = app.get_sub_app_mut(RenderApp) {
+            render_app.add_system_to_stage(
+                RenderStage::Extract,
+                extract_hash_atlas_tilemap::<T>
+                    .label(TilemapRenderSystem::ExtractTiles)
+                    .after(SpriteSystem::ExtractSprites),
+            );
+        }
+    }
+}
+
+/// Adds the system extracting `HashTilemap<T>`s drawn with individual textures
+pub struct ExtractHashTilemapPlugin<T>(PhantomData<fn() -> T>);
+
+impl<T> Default for ExtractHashTilemapPlugin<T> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<T> Plugin for ExtractHashTilemapPlugin<T>
+where
+    T: AsSpriteTile,
+{
+    fn build(&self, app: &mut App) {
+        app.add_system_to_stage(
+            CoreStage::PostUpdate,
+            calculate_hash_tilemap_bounds::<T>.label(VisibilitySystems::CalculateBounds),
+        );
+        if let Ok(render_app) = app.get_sub_app_mut(RenderApp) {
+            render_app.add_system_to_stage(
+                RenderStage::Extract,
+                extract_hash_tilemap::<T>
+                    .label(TilemapRenderSystem::ExtractTiles)
+                    .after(SpriteSystem::ExtractSprites),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::indexing::IndexableGrid;
+
+    #[derive(Clone, Debug, Default, PartialEq)]
+    struct Cell(i32);
+
+    impl Tileable for Cell {}
+
+    fn area(min: Vec2, max: Vec2) -> [Vec2; 4] {
+        [min, vec2(max.x, min.y), max, vec2(min.x, max.y)]
+    }
+
+    #[test]
+    fn signed_coordinates_and_bounds() {
+        let mut tilemap = HashTilemap::new();
+        assert_eq!(tilemap.bounds(), None);
+        tilemap.insert(ivec2(-5, 2), Cell(1));
+        tilemap.insert(ivec2(3, -7), Cell(2));
+        assert_eq!(tilemap[ivec2(-5, 2)], Cell(1));
+        assert_eq!(tilemap.get(ivec2(0, 0)), None);
+        assert_eq!(tilemap.bounds(), Some([ivec2(-5, -7), ivec2(3, 2)]));
+        assert_eq!(tilemap.remove(ivec2(3, -7)), Some(Cell(2)));
+        assert_eq!(tilemap.bounds(), Some([ivec2(-5, 2), ivec2(-5, 2)]));
+    }
+
+    #[test]
+    fn dense_round_trip() {
+        let tilemap: HashTilemap<Cell> = [(ivec2(-2, -1), Cell(1)), (ivec2(1, 3), Cell(2))]
+            .into_iter()
+            .collect();
+        let (origin, dense) = tilemap.to_tilemap(100).unwrap();
+        assert_eq!(origin, ivec2(-2, -1));
+        assert_eq!([dense.width(), dense.height()], [4, 5]);
+        assert_eq!(dense[[0, 0]], Some(Cell(1)));
+        assert_eq!(dense[[3, 4]], Some(Cell(2)));
+        assert_eq!(dense.into_iter().flatten().count(), 2);
+
+        let (origin, dense) = tilemap.to_tilemap(100).unwrap();
+        let sparse = HashTilemap::from_sparse_tilemap(&dense, origin);
+        assert_eq!(sparse.len(), 2);
+        assert_eq!(sparse[ivec2(1, 3)], Cell(2));
+
+        assert!(tilemap.to_tilemap(19).is_none());
+        let far: HashTilemap<Cell> = [(ivec2(i32::MIN, 0), Cell(1)), (ivec2(i32::MAX, 0), Cell(2))]
+            .into_iter()
+            .collect();
+        assert!(far.to_tilemap(1000).is_none());
+
+        let full =
+            HashTilemap::from_tilemap(&Tilemap::from_fn(2, 2, |x, _| Cell(x as i32)), origin);
+        assert_eq!(full.len(), 4);
+        assert_eq!(full[ivec2(-1, 0)], Cell(1));
+    }
+
+    #[test]
+    fn cells_outside_cameras_are_culled() {
+        let tilemap: HashTilemap<Cell> = (-100..100).map(|x| (ivec2(x, 0), Cell(x))).collect();
+        let geometry = TilemapGeometry {
+            tile_size: 10. * Vec2::ONE,
+            ..Default::default()
+        };
+        let transform = GlobalTransform::IDENTITY;
+        assert_eq!(
+            visible_cells(&tilemap, &geometry, &transform, &[]).count(),
+            200
+        );
+        let visible: Vec<_> = visible_cells(
+            &tilemap,
+            &geometry,
+            &transform,
+            &[area(vec2(-20., -5.), vec2(20., 5.))],
+        )
+        .collect();
+        assert!(visible.len() < 10);
+        for x in -2..=2 {
+            let (_, cell_transform) = visible.iter().find(|(tile, _)| tile.0 == x).unwrap();
+            let expected = geometry.cell_to_local([1, 1], vec2(x as f32, 0.));
+            assert!(cell_transform.translation().truncate().distance(expected) < 1e-3);
+        }
+
+        let far: HashTilemap<Cell> = [(ivec2(i32::MIN, 0), Cell(1)), (ivec2(i32::MAX, 0), Cell(2))]
+            .into_iter()
+            .collect();
+        let everywhere = area(Vec2::splat(-1e12), Vec2::splat(1e12));
+        assert_eq!(
+            visible_cells(&far, &geometry, &transform, &[everywhere]).count(),
+            2
+        );
+        let nearby = area(vec2(-20., -5.), vec2(20., 5.));
+        assert_eq!(
+            visible_cells(&far, &geometry, &transform, &[nearby]).count(),
+            0
+        );
+    }
+}
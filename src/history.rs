@@ -0,0 +1,648 @@
+use crate::blit::clip_paste_axis;
+use crate::blit::clip_region;
+use crate::indexing::IndexableGrid;
+use crate::orientation::Orientation;
+use crate::tile::Tileable;
+use crate::tilemap::Tilemap;
+use bevy::math::ivec2;
+use bevy::prelude::*;
+use std::collections::VecDeque;
+use std::marker::PhantomData;
+
+/// An edit to a tilemap that can be undone
+#[derive(Clone, Debug)]
+pub enum TilemapEdit<T>
+where
+    T: Tileable,
+{
+    /// Set the tile at `[x, y]`
+    Set { x: usize, y: usize, tile: T },
+    /// Set every cell of the rectangle with opposite corners `a` and `b`
+    Fill { a: IVec2, b: IVec2, tile: T },
+    /// Copy a region `[x, y, width, height]` of `source` with its bottom left cell at `destination`
+    Blit {
+        source: Tilemap<T>,
+        region: [usize; 4],
+        destination: IVec2,
+        orientation: Orientation,
+    },
+    /// Change the dimensions of the tilemap, setting new cells to `fill`
+    Resize {
+        width: usize,
+        height: usize,
+        fill: T,
+    },
+}
+
+/// The cells changed by an edit
+#[derive(Clone, Debug)]
+enum Change<T>
+where
+    T: Tileable,
+{
+    Cell {
+        x: usize,
+        y: usize,
+        before: T,
+        after: T,
+    },
+    Region {
+        x: usize,
+        y: usize,
+        before: Tilemap<T>,
+        after: Tilemap<T>,
+    },
+    /// a region with every cell set to `tile`
+    Fill {
+        x: usize,
+        y: usize,
+        before: Tilemap<T>,
+        tile: T,
+    },
+    /// a change of dimensions, with the regions of cells cut off by shrinking
+    Resize {
+        before: [usize; 2],
+        width: usize,
+        height: usize,
+        fill: T,
+        cut: Vec<([usize; 2], Tilemap<T>)>,
+    },
+}
+
+impl<T> Change<T>
+where
+    T: Tileable,
+{
+    fn revert(&self, tilemap: &mut Tilemap<T>) {
+        match self {
+            Change::Cell { x, y, before, .. } => Self::set(tilemap, *x, *y, before),
+            Change::Region { x, y, before, .. } | Change::Fill { x, y, before, .. } => {
+                Self::paste(tilemap, *x, *y, before)
+            }
+            Change::Resize {
+                before: [width, height],
+                cut,
+                ..
+            } => {
+                tilemap.resize(*width, *height, T::default());
+                for ([x, y], cells) in cut {
+                    Self::paste(tilemap, *x, *y, cells);
+                }
+            }
+        }
+    }
+
+    fn reapply(&self, tilemap: &mut Tilemap<T>) {
+        match self {
+            Change::Cell { x, y, after, .. } => Self::set(tilemap, *x, *y, after),
+            Change::Region { x, y, after, .. } => Self::paste(tilemap, *x, *y, after),
+            Change::Fill { x, y, before, tile } => {
+                for j in *y..(*y + before.height()).min(tilemap.height()) {
+                    for i in *x..(*x + before.width()).min(tilemap.width()) {
+                        tilemap[[i, j]] = tile.clone();
+                    }
+                }
+            }
+            Change::Resize {
+                width,
+                height,
+                fill,
+                ..
+            } => tilemap.resize(*width, *height, fill.clone()),
+        }
+    }
+
+    /// Set a cell, unless the tilemap was resized outside of the history and no longer has it
+    fn set(tilemap: &mut Tilemap<T>, x: usize, y: usize, tile: &T) {
+        if let Some(index) = tilemap.index_grid_checked(x, y) {
+            tilemap[index] = tile.clone();
+        }
+    }
+
+    fn paste(tilemap: &mut Tilemap<T>, x: usize, y: usize, cells: &Tilemap<T>) {
+        tilemap.blit(
+            cells,
+            [0, 0, cells.width(), cells.height()],
+            ivec2(x as i32, y as i32),
+            Orientation::Identity,
+        );
+    }
+}
+
+/// Bounded undo and redo history of the edits made to a tilemap
+#[derive(Component, Clone, Debug)]
+pub struct TilemapHistory<T>
+where
+    T: Tileable,
+{
+    undo: VecDeque<Vec<Change<T>>>,
+    redo: Vec<Vec<Change<T>>>,
+    group: Option<Vec<Change<T>>>,
+    group_depth: usize,
+    /// maximum number of undo steps kept
+    capacity: usize,
+}
+
+impl<T> Default for TilemapHistory<T>
+where
+    T: Tileable,
+{
+    fn default() -> Self {
+        Self::new(100)
+    }
+}
+
+impl<T> TilemapHistory<T>
+where
+    T: Tileable,
+{
+    /// History keeping up to `capacity` undo steps
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            undo: VecDeque::new(),
+            redo: Vec::new(),
+            group: None,
+            group_depth: 0,
+            capacity,
+        }
+    }
+
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    #[inline]
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty() || self.group.as_ref().is_some_and(|group| !group.is_empty())
+    }
+
+    #[inline]
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    /// Forget every recorded edit
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+        self.group = None;
+        self.group_depth = 0;
+    }
+
+    /// Record the following edits as a single undo step, until the matching [`Self::end_group`].
+    /// Groups can be nested.
+    pub fn begin_group(&mut self) {
+        self.group_depth += 1;
+        self.group.get_or_insert_with(Vec::new);
+    }
+
+    /// Close the group opened by the matching [`Self::begin_group`]
+    pub fn end_group(&mut self) {
+        self.group_depth = self.group_depth.saturating_sub(1);
+        if self.group_depth == 0 {
+            self.close_group();
+        }
+    }
+
+    fn close_group(&mut self) {
+        self.group_depth = 0;
+        if let Some(group) = self.group.take() {
+            self.push_step(group);
+        }
+    }
+
+    fn push_step(&mut self, step: Vec<Change<T>>) {
+        if step.is_empty() || self.capacity == 0 {
+            return;
+        }
+        self.undo.push_back(step);
+        while self.capacity < self.undo.len() {
+            self.undo.pop_front();
+        }
+    }
+
+    fn record(&mut self, change: Change<T>) {
+        self.redo.clear();
+        match self.group.as_mut() {
+            Some(group) => group.push(change),
+            None => self.push_step(vec![change]),
+        }
+    }
+
+    /// Modify the cells of the region `[x, y, width, height]` of `tilemap` with `f`, recording
+    /// the change. Changes `f` makes outside of the region are not recorded,
+    /// nothing is recorded if the region is entirely outside of the tilemap.
+    pub fn edit_region(
+        &mut self,
+        tilemap: &mut Tilemap<T>,
+        region: [usize; 4],
+        f: impl FnOnce(&mut Tilemap<T>),
+    ) {
+        let [x, y, width, height] = clip_region([tilemap.width(), tilemap.height()], region);
+        if width == 0 || height == 0 {
+            f(tilemap);
+            return;
+        }
+        let before = tilemap.copy_region([x, y, width, height]);
+        f(tilemap);
+        let after = tilemap.copy_region([x, y, width, height]);
+        self.record(Change::Region {
+            x,
+            y,
+            before,
+            after,
+        });
+    }
+
+    /// Apply `edit` to `tilemap`, recording the change
+    pub fn apply(&mut self, tilemap: &mut Tilemap<T>, edit: TilemapEdit<T>) {
+        match edit {
+            TilemapEdit::Set { x, y, tile } => {
+                if tilemap.index_grid_checked(x, y).is_some() {
+                    let before = std::mem::replace(&mut tilemap[[x, y]], tile.clone());
+                    self.record(Change::Cell {
+                        x,
+                        y,
+                        before,
+                        after: tile,
+                    });
+                }
+            }
+            TilemapEdit::Fill { a, b, tile } => {
+                let min = a.min(b).max(IVec2::ZERO);
+                let max = a.max(b);
+                if min.x <= max.x && min.y <= max.y {
+                    let region = [
+                        min.x as usize,
+                        min.y as usize,
+                        (max.x - min.x) as usize + 1,
+                        (max.y - min.y) as usize + 1,
+                    ];
+                    let [x, y, width, height] =
+                        clip_region([tilemap.width(), tilemap.height()], region);
+                    if 0 < width && 0 < height {
+                        let before = tilemap.copy_region([x, y, width, height]);
+                        tilemap.fill_rect(a, b, tile.clone());
+                        self.record(Change::Fill { x, y, before, tile });
+                    }
+                }
+            }
+            TilemapEdit::Blit {
+                source,
+                region,
+                destination,
+                orientation,
+            } => {
+                let [_, _, width, height] = clip_region([source.width(), source.height()], region);
+                let [width, height] = orientation.size([width, height]);
+                let (x, _, width) = clip_paste_axis(tilemap.width(), destination.x, width);
+                let (y, _, height) = clip_paste_axis(tilemap.height(), destination.y, height);
+                self.edit_region(tilemap, [x, y, width, height], |tilemap| {
+                    tilemap.blit(&source, region, destination, orientation)
+                });
+            }
+            TilemapEdit::Resize {
+                width,
+                height,
+                fill,
+            } => {
+                let before = [tilemap.width(), tilemap.height()];
+                if before == [width, height] {
+                    return;
+                }
+                let cut = [
+                    [width, 0, before[0], before[1].min(height)],
+                    [0, height, before[0], before[1]],
+                ]
+                .into_iter()
+                .map(|region| clip_region(before, region))
+                .filter(|&[_, _, width, height]| 0 < width && 0 < height)
+                .map(|region| ([region[0], region[1]], tilemap.copy_region(region)))
+                .collect();
+                tilemap.resize(width, height, fill.clone());
+                self.record(Change::Resize {
+                    before,
+                    width,
+                    height,
+                    fill,
+                    cut,
+                });
+            }
+        }
+    }
+
+    /// Revert the most recent undo step. Closes any open group.
+    /// Cells no longer on a tilemap resized outside of the history are skipped.
+    /// Returns false if there was nothing to undo.
+    pub fn undo(&mut self, tilemap: &mut Tilemap<T>) -> bool {
+        self.close_group();
+        if let Some(step) = self.undo.pop_back() {
+            for change in step.iter().rev() {
+                change.revert(tilemap);
+            }
+            self.redo.push(step);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Reapply the most recently undone step.
+    /// Returns false if there was nothing to redo.
+    pub fn redo(&mut self, tilemap: &mut Tilemap<T>) -> bool {
+        self.close_group();
+        if let Some(step) = self.redo.pop() {
+            for change in step.iter() {
+                change.reapply(tilemap);
+            }
+            self.undo.push_back(step);
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// Something to do to the tilemap and history of an entity
+#[derive(Clone, Debug)]
+pub enum TilemapHistoryAction<T>
+where
+    T: Tileable,
+{
+    Edit(TilemapEdit<T>),
+    Undo,
+    Redo,
+    BeginGroup,
+    EndGroup,
+}
+
+/// Send to apply an action to the `Tilemap<T>` and `TilemapHistory<T>` of `entity`
+#[derive(Clone, Debug)]
+pub struct TilemapHistoryEvent<T>
+where
+    T: Tileable,
+{
+    pub entity: Entity,
+    pub action: TilemapHistoryAction<T>,
+}
+
+pub fn apply_tilemap_history_events<T>(
+    mut events: EventReader<TilemapHistoryEvent<T>>,
+    mut tilemap_query: Query<(&mut Tilemap<T>, &mut TilemapHistory<T>)>,
+) where
+    T: Tileable,
+{
+    for event in events.iter() {
+        if let Ok((mut tilemap, mut history)) = tilemap_query.get_mut(event.entity) {
+            match event.action.clone() {
+                TilemapHistoryAction::Edit(edit) => history.apply(&mut tilemap, edit),
+                TilemapHistoryAction::Undo => {
+                    history.undo(&mut tilemap);
+                }
+                TilemapHistoryAction::Redo => {
+                    history.redo(&mut tilemap);
+                }
+                TilemapHistoryAction::BeginGroup => history.begin_group(),
+                TilemapHistoryAction::EndGroup => history.end_group(),
+            }
+        }
+    }
+}
+
+/// Adds [`TilemapHistoryEvent<T>`] and the system that applies them
+pub struct TilemapHistoryPlugin<T>(PhantomData<fn() -> T>);
+
+impl<T> Default for TilemapHistoryPlugin<T> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<T> Plugin for TilemapHistoryPlugin<T>
+where
+    T: Tileable,
+{
+    fn build(&self, app: &mut App) {
+        app.add_event::<TilemapHistoryEvent<T>>()
+            .add_system(apply_tilemap_history_events::<T>);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Clone, Debug, Default, PartialEq)]
+    struct Cell(u32);
+
+    impl Tileable for Cell {}
+
+    fn cells(tilemap: &Tilemap<Cell>) -> Vec<u32> {
+        tilemap.into_iter().map(|cell| cell.0).collect()
+    }
+
+    fn set(x: usize, y: usize, n: u32) -> TilemapEdit<Cell> {
+        TilemapEdit::Set {
+            x,
+            y,
+            tile: Cell(n),
+        }
+    }
+
+    #[test]
+    fn undo_redo_round_trip() {
+        let mut tilemap = Tilemap::<Cell>::from_default(3, 2);
+        let mut history = TilemapHistory::default();
+        history.apply(&mut tilemap, set(1, 0, 5));
+        history.apply(
+            &mut tilemap,
+            TilemapEdit::Fill {
+                a: ivec2(2, 1),
+                b: ivec2(0, 1),
+                tile: Cell(7),
+            },
+        );
+        let mut source = Tilemap::<Cell>::from_elem(2, 2, Cell(9));
+        source[[0, 0]] = Cell(8);
+        history.apply(
+            &mut tilemap,
+            TilemapEdit::Blit {
+                source,
+                region: [0, 0, 2, 2],
+                destination: ivec2(2, 0),
+                orientation: Orientation::Identity,
+            },
+        );
+        let edited = vec![0, 5, 8, 7, 7, 9];
+        assert_eq!(cells(&tilemap), edited);
+
+        assert!(history.undo(&mut tilemap));
+        assert_eq!(cells(&tilemap), vec![0, 5, 0, 7, 7, 7]);
+        assert!(history.undo(&mut tilemap));
+        assert!(history.undo(&mut tilemap));
+        assert_eq!(cells(&tilemap), vec![0; 6]);
+        assert!(!history.undo(&mut tilemap));
+
+        assert!(history.redo(&mut tilemap));
+        assert!(history.redo(&mut tilemap));
+        assert!(history.redo(&mut tilemap));
+        assert_eq!(cells(&tilemap), edited);
+        assert!(!history.redo(&mut tilemap));
+    }
+
+    #[test]
+    fn group_undoes_as_one_step() {
+        let mut tilemap = Tilemap::<Cell>::from_default(2, 2);
+        let mut history = TilemapHistory::default();
+        history.apply(&mut tilemap, set(0, 0, 1));
+        history.begin_group();
+        history.apply(&mut tilemap, set(1, 0, 2));
+        history.begin_group();
+        history.apply(&mut tilemap, set(0, 1, 3));
+        history.end_group();
+        history.apply(&mut tilemap, set(1, 1, 4));
+        history.end_group();
+        assert_eq!(cells(&tilemap), vec![1, 2, 3, 4]);
+
+        assert!(history.undo(&mut tilemap));
+        assert_eq!(cells(&tilemap), vec![1, 0, 0, 0]);
+        assert!(history.redo(&mut tilemap));
+        assert_eq!(cells(&tilemap), vec![1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn capacity_drops_oldest_steps() {
+        let mut tilemap = Tilemap::<Cell>::from_default(4, 1);
+        let mut history = TilemapHistory::new(2);
+        for x in 0..4 {
+            history.apply(&mut tilemap, set(x, 0, 1));
+        }
+        assert!(history.undo(&mut tilemap));
+        assert!(history.undo(&mut tilemap));
+        assert!(!history.undo(&mut tilemap));
+        assert_eq!(cells(&tilemap), vec![1, 1, 0, 0]);
+    }
+
+    #[test]
+    fn new_edit_clears_redo() {
+        let mut tilemap = Tilemap::<Cell>::from_default(2, 1);
+        let mut history = TilemapHistory::default();
+        history.apply(&mut tilemap, set(0, 0, 1));
+        history.undo(&mut tilemap);
+        assert!(history.can_redo());
+        history.apply(&mut tilemap, set(1, 0, 2));
+        assert!(!history.can_redo());
+        assert!(!history.redo(&mut tilemap));
+        assert_eq!(cells(&tilemap), vec![0, 2]);
+    }
+
+    #[test]
+    fn edits_off_the_map_are_not_recorded() {
+        let mut tilemap = Tilemap::<Cell>::from_default(2, 2);
+        let mut history = TilemapHistory::default();
+        history.apply(&mut tilemap, set(2, 0, 1));
+        history.apply(
+            &mut tilemap,
+            TilemapEdit::Fill {
+                a: ivec2(-5, 0),
+                b: ivec2(-1, 1),
+                tile: Cell(1),
+            },
+        );
+        history.apply(
+            &mut tilemap,
+            TilemapEdit::Fill {
+                a: ivec2(3, 3),
+                b: ivec2(5, 5),
+                tile: Cell(1),
+            },
+        );
+        history.apply(
+            &mut tilemap,
+            TilemapEdit::Blit {
+                source: Tilemap::from_default(2, 2),
+                region: [0, 0, 2, 2],
+                destination: ivec2(10, 0),
+                orientation: Orientation::Identity,
+            },
+        );
+        history.apply(
+            &mut tilemap,
+            TilemapEdit::Resize {
+                width: 2,
+                height: 2,
+                fill: Cell(1),
+            },
+        );
+        assert!(!history.can_undo());
+    }
+
+    #[test]
+    fn resize_round_trip() {
+        let mut tilemap = Tilemap::from_fn(3, 3, |x, y| Cell(1 + x as u32 + 3 * y as u32));
+        let original = cells(&tilemap);
+        let mut history = TilemapHistory::default();
+        history.apply(
+            &mut tilemap,
+            TilemapEdit::Resize {
+                width: 2,
+                height: 4,
+                fill: Cell(0),
+            },
+        );
+        history.apply(
+            &mut tilemap,
+            TilemapEdit::Resize {
+                width: 4,
+                height: 1,
+                fill: Cell(0),
+            },
+        );
+        assert_eq!(cells(&tilemap), vec![1, 2, 0, 0]);
+        assert!(history.undo(&mut tilemap));
+        assert_eq!(cells(&tilemap), vec![1, 2, 4, 5, 7, 8, 0, 0]);
+        assert!(history.undo(&mut tilemap));
+        assert_eq!(cells(&tilemap), original);
+        assert!(history.redo(&mut tilemap));
+        assert!(history.redo(&mut tilemap));
+        assert_eq!(cells(&tilemap), vec![1, 2, 0, 0]);
+    }
+
+    #[test]
+    fn cells_cut_outside_the_history_are_skipped() {
+        let mut tilemap = Tilemap::<Cell>::from_default(3, 3);
+        let mut history = TilemapHistory::default();
+        history.apply(&mut tilemap, set(2, 2, 1));
+        history.apply(
+            &mut tilemap,
+            TilemapEdit::Fill {
+                a: ivec2(1, 1),
+                b: ivec2(2, 2),
+                tile: Cell(2),
+            },
+        );
+        history.apply(
+            &mut tilemap,
+            TilemapEdit::Blit {
+                source: Tilemap::from_elem(2, 2, Cell(3)),
+                region: [0, 0, 2, 2],
+                destination: ivec2(1, 0),
+                orientation: Orientation::Identity,
+            },
+        );
+        tilemap.resize(2, 2, Cell(0));
+        assert_eq!(cells(&tilemap), vec![0, 3, 0, 3]);
+
+        assert!(history.undo(&mut tilemap));
+        assert_eq!(cells(&tilemap), vec![0, 0, 0, 2]);
+        assert!(history.undo(&mut tilemap));
+        assert!(history.undo(&mut tilemap));
+        assert_eq!(cells(&tilemap), vec![0; 4]);
+        assert!(history.redo(&mut tilemap));
+        assert!(history.redo(&mut tilemap));
+        assert_eq!(cells(&tilemap), vec![0, 0, 0, 2]);
+        assert!(history.redo(&mut tilemap));
+        assert_eq!(cells(&tilemap), vec![0, 3, 0, 3]);
+    }
+}
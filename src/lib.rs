@@ -1,25 +1,93 @@
+pub mod blit;
 pub mod bundles;
+pub mod cell_sizes;
+pub mod compact;
+pub mod debug;
+pub mod draw;
 pub mod extractable_tilemaps;
 pub mod extraction;
 pub mod geometry;
+pub mod hash_tilemap;
+pub mod history;
 pub mod indexing;
+pub mod movement;
+pub mod occupancy;
+pub mod orientation;
+pub mod parallax;
+pub mod patch;
+pub mod rules;
+pub mod shared;
 pub mod tile;
 pub mod tilemap;
+pub mod ui;
 pub mod util;
+pub mod variants;
+pub mod world;
 
 use crate::geometry::*;
 
 use bevy::prelude::*;
+use bevy::transform::TransformSystem;
 
 pub mod prelude {
     pub use crate::bundles::*;
+    pub use crate::cell_sizes::TilemapCellSizes;
+    pub use crate::compact::AsCompactTile;
+    pub use crate::compact::CompactTile;
+    pub use crate::compact::CompactTilemapBundle;
+    pub use crate::compact::ExtractCompactTilemapPlugin;
+    pub use crate::compact::TilePalette;
+    pub use crate::debug::TilemapDebugOverlayPlugin;
+    pub use crate::debug::TilemapDebugPlugin;
+    pub use crate::debug::TilemapDebugSettings;
+    pub use crate::draw::Brush;
+    pub use crate::extractable_tilemaps::AsAtlasTile;
+    pub use crate::extractable_tilemaps::AsSpriteTile;
+    pub use crate::extraction::ExtractAtlasTilemapPlugin;
+    pub use crate::extraction::ExtractTilemapPlugin;
+    pub use crate::extraction::TilemapExtractionSettings;
+    pub use crate::geometry::CellPlacement;
+    pub use crate::geometry::TileFit;
     pub use crate::geometry::TilemapGeometry;
     pub use crate::geometry::TilemapView;
+    pub use crate::hash_tilemap::ExtractHashAtlasTilemapPlugin;
+    pub use crate::hash_tilemap::ExtractHashTilemapPlugin;
+    pub use crate::hash_tilemap::HashAtlasTilemapBundle;
+    pub use crate::hash_tilemap::HashSpriteTilemapBundle;
+    pub use crate::hash_tilemap::HashTilemap;
+    pub use crate::history::TilemapEdit;
+    pub use crate::history::TilemapHistory;
+    pub use crate::history::TilemapHistoryAction;
+    pub use crate::history::TilemapHistoryEvent;
+    pub use crate::history::TilemapHistoryPlugin;
     pub use crate::indexing::*;
+    pub use crate::movement::AlignTilePositionsPlugin;
+    pub use crate::movement::TileMovement;
+    pub use crate::movement::TileMovementPlugin;
+    pub use crate::movement::TileOffset;
+    pub use crate::occupancy::TilePosition;
+    pub use crate::occupancy::TilemapOccupancy;
+    pub use crate::occupancy::TilemapOccupancyPlugin;
+    pub use crate::orientation::Orientation;
+    pub use crate::parallax::TilemapParallax;
+    pub use crate::patch::TileRun;
+    pub use crate::patch::TilemapPatch;
+    pub use crate::rules::NeighborCondition;
+    pub use crate::rules::Orientable;
+    pub use crate::rules::RuleOutput;
+    pub use crate::rules::RuleTile;
+    pub use crate::rules::TileRule;
+    pub use crate::shared::TilemapSource;
+    pub use crate::shared::TilemapViewBundle;
     pub use crate::tile::SpriteTile;
     pub use crate::tile::TextureAtlasTile;
     pub use crate::tile::Tileable;
     pub use crate::tilemap::*;
+    pub use crate::ui::UiTilemapBundle;
+    pub use crate::ui::UiTilemapScaling;
+    pub use crate::variants::TileVariants;
+    pub use crate::world::TilemapPlacement;
+    pub use crate::world::TilemapWorld;
     pub use crate::SpriteTilemapPlugin;
 }
 
@@ -29,6 +97,12 @@ impl Plugin for SpriteTilemapPlugin {
     fn build(&self, app: &mut App) {
         app.register_type::<TilemapGeometry>()
             .register_type::<TilemapView>()
-            .add_plugin(extraction::TilemapExtractionPlugin);
+            .register_type::<world::TilemapPlacement>()
+            .add_plugin(extraction::TilemapExtractionPlugin)
+            .add_plugin(ui::UiTilemapPlugin)
+            .add_system_to_stage(
+                CoreStage::PostUpdate,
+                parallax::update_tilemap_parallax.before(TransformSystem::TransformPropagate),
+            );
     }
 }
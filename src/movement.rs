@@ -0,0 +1,291 @@
+use crate::cell_sizes::TilemapCellSizes;
+use crate::compact::CompactTile;
+use crate::geometry::TilemapGeometry;
+use crate::geometry::TilemapView;
+use crate::indexing::IndexableGrid;
+use crate::occupancy::TilePosition;
+use crate::tile::SpriteTile;
+use crate::tile::TextureAtlasTile;
+use crate::tilemap::Tilemap;
+use crate::util::cell_to_world;
+use bevy::prelude::*;
+use bevy::transform::TransformSystem;
+use std::marker::PhantomData;
+
+/// Offset in cells from the centre of the cell of an entity's [`TilePosition`],
+/// only used to position the entity
+#[derive(Clone, Copy, Component, Debug, Default, PartialEq)]
+pub struct TileOffset(pub Vec2);
+
+/// Moves an entity with a [`TilePosition`] smoothly to its cell instead of jumping there
+#[derive(Clone, Copy, Component, Debug, PartialEq)]
+pub struct TileMovement {
+    /// cells moved per second
+    pub speed: f32,
+    /// the tilemap moved on and the current point on it in cell coordinates
+    current: Option<(Entity, Vec2)>,
+}
+
+impl TileMovement {
+    pub fn new(speed: f32) -> Self {
+        Self {
+            speed,
+            current: None,
+        }
+    }
+
+    /// The point the entity is drawn at in cell coordinates, `None` before it is first aligned
+    #[inline]
+    pub fn current(&self) -> Option<Vec2> {
+        self.current.map(|(_, point)| point)
+    }
+
+    /// True if the entity hasn't reached `position` yet
+    pub fn is_moving(&self, position: &TilePosition) -> bool {
+        self.current != Some((position.tilemap, position.point()))
+    }
+
+    /// Jump straight to the next position instead of moving to it
+    #[inline]
+    pub fn snap(&mut self) {
+        self.current = None;
+    }
+
+    /// Move `delta_seconds` worth of the way to `position`, returning the new current point.
+    /// Moving to a different tilemap jumps straight there.
+    pub fn step(&mut self, position: &TilePosition, delta_seconds: f32) -> Vec2 {
+        let target = position.point();
+        let point = match self.current {
+            Some((tilemap, current)) if tilemap == position.tilemap => {
+                let remaining = target - current;
+                let distance = self.speed.max(0.) * delta_seconds;
+                if remaining.length() <= distance {
+                    target
+                } else {
+                    current + remaining.normalize() * distance
+                }
+            }
+            _ => target,
+        };
+        self.current = Some((position.tilemap, point));
+        point
+    }
+}
+
+/// Keeps the translation of each entity with a [`TilePosition`] on a `T` tilemap
+/// over its cell, moving it there gradually if it has a [`TileMovement`]
+/// and offset from the cell's centre by its [`TileOffset`].
+///
+/// The entity is placed over its cell in world space, keeping its depth.
+/// Entities with a parent are placed through the parent's `GlobalTransform`,
+/// which like the tilemap's is the one from the end of the previous frame.
+#[allow(clippy::type_complexity)]
+pub fn align_tile_positions<T>(
+    time: Res<Time>,
+    tilemap_query: Query<(
+        &T,
+        &TilemapGeometry,
+        &TilemapView,
+        Option<&TilemapCellSizes>,
+        &GlobalTransform,
+    )>,
+    parent_query: Query<&GlobalTransform>,
+    mut position_query: Query<(
+        &TilePosition,
+        Option<&TileOffset>,
+        Option<&mut TileMovement>,
+        Option<&Parent>,
+        &mut Transform,
+    )>,
+) where
+    T: Component + IndexableGrid,
+{
+    for (position, offset, movement, parent, mut transform) in position_query.iter_mut() {
+        let (tilemap, geometry, view, cell_sizes, tilemap_transform) =
+            match tilemap_query.get(position.tilemap) {
+                Ok(tilemap) => tilemap,
+                Err(_) => continue,
+            };
+        let point = match movement {
+            Some(mut movement) => movement.step(position, time.delta_seconds()),
+            None => position.point(),
+        } + offset.map_or(Vec2::ZERO, |offset| offset.0);
+        let world = match cell_sizes {
+            Some(cell_sizes) => match cell_sizes.point_to_local(geometry, point) {
+                Some(local) => tilemap_transform.transform_point(local.extend(0.)),
+                None => continue,
+            },
+            None => cell_to_world(
+                point,
+                tilemap_transform,
+                tilemap.width(),
+                tilemap.height(),
+                geometry,
+                view,
+            ),
+        };
+        let translation = match parent.and_then(|parent| parent_query.get(parent.get()).ok()) {
+            Some(parent_transform) => {
+                let depth = parent_transform.transform_point(transform.translation).z;
+                parent_transform
+                    .affine()
+                    .inverse()
+                    .transform_point3(world.truncate().extend(depth))
+            }
+            None => world.truncate().extend(transform.translation.z),
+        };
+        if transform.translation != translation {
+            transform.translation = translation;
+        }
+    }
+}
+
+/// Adds the system aligning entities to their [`TilePosition`]s on tilemap components of type `T`,
+/// see [`align_tile_positions`]
+pub struct AlignTilePositionsPlugin<T>(PhantomData<fn() -> T>);
+
+impl<T> Default for AlignTilePositionsPlugin<T> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<T> Plugin for AlignTilePositionsPlugin<T>
+where
+    T: Component + IndexableGrid,
+{
+    fn build(&self, app: &mut App) {
+        app.add_system_to_stage(
+            CoreStage::PostUpdate,
+            align_tile_positions::<T>.before(TransformSystem::TransformPropagate),
+        );
+    }
+}
+
+/// Aligns entities to their [`TilePosition`]s on tilemaps of the tile types drawn by
+/// [`SpriteTilemapPlugin`](crate::SpriteTilemapPlugin).
+///
+/// Add an [`AlignTilePositionsPlugin`] for each of your own tilemap types.
+pub struct TileMovementPlugin;
+
+impl Plugin for TileMovementPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugin(AlignTilePositionsPlugin::<Tilemap<TextureAtlasTile>>::default())
+            .add_plugin(AlignTilePositionsPlugin::<Tilemap<Option<TextureAtlasTile>>>::default())
+            .add_plugin(AlignTilePositionsPlugin::<Tilemap<SpriteTile>>::default())
+            .add_plugin(AlignTilePositionsPlugin::<Tilemap<Option<SpriteTile>>>::default())
+            .add_plugin(AlignTilePositionsPlugin::<Tilemap<CompactTile>>::default())
+            .add_plugin(AlignTilePositionsPlugin::<Tilemap<Option<CompactTile>>>::default());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::math::vec2;
+
+    #[test]
+    fn movement_reaches_its_cell() {
+        let tilemap = Entity::from_raw(0);
+        let mut movement = TileMovement::new(2.);
+        let mut position = TilePosition::new(tilemap, [1, 1]);
+        assert_eq!(movement.step(&position, 0.1), vec2(1., 1.));
+        assert!(!movement.is_moving(&position));
+
+        position.cell = [3, 1];
+        assert!(movement.is_moving(&position));
+        assert_eq!(movement.step(&position, 0.25), vec2(1.5, 1.));
+        assert_eq!(movement.step(&position, 0.5), vec2(2.5, 1.));
+        assert_eq!(movement.step(&position, 0.5), vec2(3., 1.));
+        assert!(!movement.is_moving(&position));
+
+        let elsewhere = TilePosition::new(Entity::from_raw(1), [0, 0]);
+        assert_eq!(movement.step(&elsewhere, 0.01), Vec2::ZERO);
+        movement.snap();
+        assert_eq!(movement.current(), None);
+    }
+
+    #[test]
+    fn aligned_over_rotated_and_scaled_tilemap() {
+        let mut world = World::new();
+        world.insert_resource(Time::default());
+        let transform = Transform {
+            translation: Vec3::new(100., 50., 0.),
+            rotation: Quat::from_rotation_z(0.5),
+            scale: Vec3::splat(3.),
+        };
+        let geometry = TilemapGeometry::default();
+        let tilemap = world
+            .spawn((
+                Tilemap::<TextureAtlasTile>::from_default(5, 4),
+                geometry.clone(),
+                TilemapView::All,
+                GlobalTransform::from(transform),
+            ))
+            .id();
+        let unit = world
+            .spawn((
+                TilePosition::new(tilemap, [3, 2]),
+                Transform::from_xyz(0., 0., 7.),
+            ))
+            .id();
+        let mut stage = SystemStage::single(align_tile_positions::<Tilemap<TextureAtlasTile>>);
+        stage.run(&mut world);
+        let translation = world.get::<Transform>(unit).unwrap().translation;
+        assert_eq!(translation.z, 7.);
+        let picked = crate::util::pick_tile(
+            translation.truncate(),
+            &GlobalTransform::from(transform),
+            5,
+            4,
+            &geometry,
+            &TilemapView::All,
+            None,
+        );
+        assert_eq!(picked, Some([3, 2]));
+    }
+
+    #[test]
+    fn aligned_through_parent_with_offset() {
+        let mut world = World::new();
+        world.insert_resource(Time::default());
+        let geometry = TilemapGeometry::default();
+        let tilemap = world
+            .spawn((
+                Tilemap::<TextureAtlasTile>::from_default(5, 4),
+                geometry.clone(),
+                TilemapView::All,
+                GlobalTransform::IDENTITY,
+            ))
+            .id();
+        let parent_transform = GlobalTransform::from(Transform {
+            translation: Vec3::new(10., 20., 5.),
+            rotation: Quat::from_rotation_z(1.),
+            scale: Vec3::splat(2.),
+        });
+        let parent = world.spawn(parent_transform).id();
+        let unit = world
+            .spawn((
+                TilePosition::new(tilemap, [3, 2]),
+                TileOffset(vec2(0.5, 0.)),
+                Transform::from_xyz(0., 0., 1.),
+            ))
+            .id();
+        world.entity_mut(parent).push_children(&[unit]);
+        let mut stage = SystemStage::single(align_tile_positions::<Tilemap<TextureAtlasTile>>);
+        stage.run(&mut world);
+
+        let translation = world.get::<Transform>(unit).unwrap().translation;
+        let placed = parent_transform.transform_point(translation);
+        let expected = cell_to_world(
+            vec2(3.5, 2.),
+            &GlobalTransform::IDENTITY,
+            5,
+            4,
+            &geometry,
+            &TilemapView::All,
+        );
+        assert!(placed.truncate().distance(expected.truncate()) < 1e-3);
+        assert!((placed.z - 7.).abs() < 1e-3);
+    }
+}
@@ -0,0 +1,294 @@
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+
+/// The cell of a tilemap an entity stands on
+#[derive(Clone, Copy, Component, Debug, PartialEq, Eq, Hash)]
+pub struct TilePosition {
+    /// the tilemap entity
+    pub tilemap: Entity,
+    /// the cell on the tilemap
+    pub cell: [usize; 2],
+}
+
+impl TilePosition {
+    pub fn new(tilemap: Entity, cell: [usize; 2]) -> Self {
+        Self { tilemap, cell }
+    }
+
+    /// The centre of the cell in cell coordinates
+    #[inline]
+    pub fn point(&self) -> Vec2 {
+        Vec2::new(self.cell[0] as f32, self.cell[1] as f32)
+    }
+}
+
+/// Index of the entities standing on each cell of a tilemap.
+///
+/// Inserted on a tilemap entity and kept up to date from the [`TilePosition`]s of the
+/// entities on it by the [`TilemapOccupancyPlugin`], after the systems of the `Update` stage.
+#[derive(Clone, Component, Debug, Default)]
+pub struct TilemapOccupancy {
+    cells: HashMap<[usize; 2], Vec<Entity>>,
+}
+
+impl TilemapOccupancy {
+    /// Number of entities in the index
+    pub fn len(&self) -> usize {
+        self.cells.values().map(Vec::len).sum()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.cells.is_empty()
+    }
+
+    /// True if any entity stands on `cell`
+    #[inline]
+    pub fn is_occupied(&self, cell: [usize; 2]) -> bool {
+        self.cells.contains_key(&cell)
+    }
+
+    /// The entities standing on `cell`, in the order they arrived
+    #[inline]
+    pub fn entities_at(&self, cell: [usize; 2]) -> &[Entity] {
+        self.cells.get(&cell).map_or(&[], Vec::as_slice)
+    }
+
+    /// Every occupied cell with the entities standing on it
+    pub fn iter(&self) -> impl Iterator<Item = ([usize; 2], &[Entity])> {
+        self.cells
+            .iter()
+            .map(|(&cell, entities)| (cell, entities.as_slice()))
+    }
+
+    /// The entities standing on the cells from `min` to `max` inclusive, with their cells
+    pub fn entities_in_rect(
+        &self,
+        min: [usize; 2],
+        max: [usize; 2],
+    ) -> impl Iterator<Item = ([usize; 2], Entity)> + '_ {
+        let contains =
+            move |[x, y]: [usize; 2]| min[0] <= x && x <= max[0] && min[1] <= y && y <= max[1];
+        let area = max[0]
+            .saturating_sub(min[0])
+            .saturating_add(1)
+            .saturating_mul(max[1].saturating_sub(min[1]).saturating_add(1));
+        // look up each cell of small rectangles, filter the occupied cells for large ones
+        let cells: Vec<[usize; 2]> = if area <= self.cells.len() {
+            (min[1]..=max[1])
+                .flat_map(|y| (min[0]..=max[0]).map(move |x| [x, y]))
+                .filter(|cell| self.cells.contains_key(cell))
+                .collect()
+        } else {
+            self.cells
+                .keys()
+                .copied()
+                .filter(|&cell| contains(cell))
+                .collect()
+        };
+        cells.into_iter().flat_map(move |cell| {
+            self.entities_at(cell)
+                .iter()
+                .map(move |&entity| (cell, entity))
+        })
+    }
+
+    /// The entities standing on cells within `radius` cells of `centre`, with their cells
+    pub fn entities_within(
+        &self,
+        centre: [usize; 2],
+        radius: f32,
+    ) -> impl Iterator<Item = ([usize; 2], Entity)> + '_ {
+        let reach = radius.max(0.) as usize;
+        let min = [
+            centre[0].saturating_sub(reach),
+            centre[1].saturating_sub(reach),
+        ];
+        let max = [
+            centre[0].saturating_add(reach),
+            centre[1].saturating_add(reach),
+        ];
+        self.entities_in_rect(min, max).filter(move |&([x, y], _)| {
+            let dx = x.abs_diff(centre[0]) as f32;
+            let dy = y.abs_diff(centre[1]) as f32;
+            dx * dx + dy * dy <= radius * radius
+        })
+    }
+
+    fn insert(&mut self, cell: [usize; 2], entity: Entity) {
+        self.cells.entry(cell).or_default().push(entity);
+    }
+
+    fn remove(&mut self, cell: [usize; 2], entity: Entity) {
+        if let Some(entities) = self.cells.get_mut(&cell) {
+            entities.retain(|&other| other != entity);
+            if entities.is_empty() {
+                self.cells.remove(&cell);
+            }
+        }
+    }
+}
+
+/// Remove `entity` from `cell` in the index of `tilemap`
+fn vacate(
+    occupancy_query: &mut Query<&mut TilemapOccupancy>,
+    inserted: &mut HashMap<Entity, TilemapOccupancy>,
+    (tilemap, cell): (Entity, [usize; 2]),
+    entity: Entity,
+) {
+    match occupancy_query.get_mut(tilemap) {
+        Ok(mut occupancy) => occupancy.remove(cell, entity),
+        Err(_) => {
+            if let Some(occupancy) = inserted.get_mut(&tilemap) {
+                occupancy.remove(cell, entity);
+            }
+        }
+    }
+}
+
+/// Label of the system updating the [`TilemapOccupancy`] of each tilemap
+#[derive(Debug, Hash, PartialEq, Eq, Clone, SystemLabel)]
+pub struct TilemapOccupancySystem;
+
+/// Moves entities whose [`TilePosition`] changed or was removed to their new cells
+/// in the [`TilemapOccupancy`] of their tilemaps,
+/// inserting a `TilemapOccupancy` on tilemaps without one
+pub fn update_tilemap_occupancy(
+    mut commands: Commands,
+    mut indexed: Local<HashMap<Entity, (Entity, [usize; 2])>>,
+    removed: RemovedComponents<TilePosition>,
+    position_query: Query<(Entity, &TilePosition), Changed<TilePosition>>,
+    mut occupancy_query: Query<&mut TilemapOccupancy>,
+) {
+    let mut inserted: HashMap<Entity, TilemapOccupancy> = HashMap::default();
+    for entity in removed.iter() {
+        if let Some(previous) = indexed.remove(&entity) {
+            vacate(&mut occupancy_query, &mut inserted, previous, entity);
+        }
+    }
+    for (entity, &position) in position_query.iter() {
+        match indexed.insert(entity, (position.tilemap, position.cell)) {
+            Some(previous) if previous == (position.tilemap, position.cell) => continue,
+            Some(previous) => vacate(&mut occupancy_query, &mut inserted, previous, entity),
+            None => {}
+        }
+        match occupancy_query.get_mut(position.tilemap) {
+            Ok(mut occupancy) => occupancy.insert(position.cell, entity),
+            Err(_) => inserted
+                .entry(position.tilemap)
+                .or_default()
+                .insert(position.cell, entity),
+        }
+    }
+    for (tilemap, occupancy) in inserted {
+        if let Some(mut tilemap) = commands.get_entity(tilemap) {
+            tilemap.insert(occupancy);
+        }
+    }
+}
+
+/// Keeps the [`TilemapOccupancy`] of each tilemap in sync with the [`TilePosition`]s of the entities on it
+pub struct TilemapOccupancyPlugin;
+
+impl Plugin for TilemapOccupancyPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_to_stage(
+            CoreStage::PostUpdate,
+            update_tilemap_occupancy.label(TilemapOccupancySystem),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn occupancy(entities: &[([usize; 2], u32)]) -> TilemapOccupancy {
+        let mut occupancy = TilemapOccupancy::default();
+        for &(cell, id) in entities {
+            occupancy.insert(cell, Entity::from_raw(id));
+        }
+        occupancy
+    }
+
+    #[test]
+    fn unbounded_queries_filter_occupied_cells() {
+        let occupancy = occupancy(&[([0, 0], 0), ([2, 1], 1), ([usize::MAX, 7], 2)]);
+        assert_eq!(
+            occupancy
+                .entities_in_rect([0, 0], [usize::MAX, usize::MAX])
+                .count(),
+            3
+        );
+        assert_eq!(
+            occupancy.entities_in_rect([0, 1], [usize::MAX, 1]).count(),
+            1
+        );
+        assert_eq!(occupancy.entities_within([2, 1], f32::INFINITY).count(), 3);
+        assert_eq!(occupancy.entities_within([2, 1], 1e30).count(), 3);
+    }
+
+    #[test]
+    fn rectangle_and_radius_queries() {
+        let occupancy = occupancy(&[([0, 0], 0), ([2, 1], 1), ([2, 1], 2), ([5, 5], 3)]);
+        assert_eq!(occupancy.len(), 4);
+        assert_eq!(
+            occupancy.entities_at([2, 1]),
+            &[Entity::from_raw(1), Entity::from_raw(2)]
+        );
+        assert!(occupancy.entities_at([1, 1]).is_empty());
+
+        let mut in_rect: Vec<_> = occupancy
+            .entities_in_rect([0, 0], [2, 2])
+            .map(|(_, entity)| entity.index())
+            .collect();
+        in_rect.sort();
+        assert_eq!(in_rect, vec![0, 1, 2]);
+        assert_eq!(occupancy.entities_in_rect([1, 1], [1, 1]).count(), 0);
+        assert_eq!(occupancy.entities_in_rect([0, 0], [100, 100]).count(), 4);
+
+        assert_eq!(occupancy.entities_within([1, 0], 1.).count(), 1);
+        assert_eq!(occupancy.entities_within([1, 0], 1.5).count(), 3);
+        assert_eq!(occupancy.entities_within([5, 4], 0.).count(), 0);
+    }
+
+    #[test]
+    fn index_follows_positions() {
+        let mut world = World::new();
+        let mut stage = SystemStage::single(update_tilemap_occupancy);
+        let tilemap = world.spawn(TilemapOccupancy::default()).id();
+        let unit = world.spawn(TilePosition::new(tilemap, [1, 1])).id();
+        let other = world.spawn(TilePosition::new(tilemap, [1, 1])).id();
+        stage.run(&mut world);
+        world.clear_trackers();
+        let occupancy = world.get::<TilemapOccupancy>(tilemap).unwrap();
+        assert_eq!(occupancy.entities_at([1, 1]), &[unit, other]);
+
+        world.get_mut::<TilePosition>(unit).unwrap().cell = [2, 1];
+        stage.run(&mut world);
+        world.clear_trackers();
+        let occupancy = world.get::<TilemapOccupancy>(tilemap).unwrap();
+        assert_eq!(occupancy.entities_at([1, 1]), &[other]);
+        assert_eq!(occupancy.entities_at([2, 1]), &[unit]);
+
+        world.despawn(other);
+        stage.run(&mut world);
+        world.clear_trackers();
+        let occupancy = world.get::<TilemapOccupancy>(tilemap).unwrap();
+        assert!(!occupancy.is_occupied([1, 1]));
+        assert_eq!(occupancy.len(), 1);
+
+        let new_tilemap = world.spawn_empty().id();
+        world.get_mut::<TilePosition>(unit).unwrap().tilemap = new_tilemap;
+        stage.run(&mut world);
+        world.clear_trackers();
+        assert!(world.get::<TilemapOccupancy>(tilemap).unwrap().is_empty());
+        assert_eq!(
+            world
+                .get::<TilemapOccupancy>(new_tilemap)
+                .unwrap()
+                .entities_at([2, 1]),
+            &[unit]
+        );
+    }
+}
@@ -0,0 +1,137 @@
+use bevy::prelude::*;
+
+/// One of the eight rotations and reflections of a rectangular grid
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Reflect)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Orientation {
+    #[default]
+    Identity,
+    /// Rotate a quarter turn anticlockwise
+    Rotate90,
+    /// Rotate a half turn
+    Rotate180,
+    /// Rotate a quarter turn clockwise
+    Rotate270,
+    /// Mirror along the `X` axis, swapping left and right
+    FlipX,
+    /// Mirror along the `Y` axis, swapping top and bottom
+    FlipY,
+    /// Mirror along the diagonal through the bottom left cell, swapping `x` and `y`
+    Transpose,
+    /// Mirror along the diagonal through the top left cell
+    AntiTranspose,
+}
+
+impl Orientation {
+    pub const ALL: [Orientation; 8] = [
+        Orientation::Identity,
+        Orientation::Rotate90,
+        Orientation::Rotate180,
+        Orientation::Rotate270,
+        Orientation::FlipX,
+        Orientation::FlipY,
+        Orientation::Transpose,
+        Orientation::AntiTranspose,
+    ];
+
+    /// The four rotations, without reflections
+    pub const ROTATIONS: [Orientation; 4] = [
+        Orientation::Identity,
+        Orientation::Rotate90,
+        Orientation::Rotate180,
+        Orientation::Rotate270,
+    ];
+
+    /// The orientation that undoes this one
+    #[inline]
+    pub fn inverse(self) -> Self {
+        match self {
+            Orientation::Rotate90 => Orientation::Rotate270,
+            Orientation::Rotate270 => Orientation::Rotate90,
+            other => other,
+        }
+    }
+
+    /// True if the orientation swaps the axes of the grid
+    #[inline]
+    pub fn swaps_axes(self) -> bool {
+        matches!(
+            self,
+            Orientation::Rotate90
+                | Orientation::Rotate270
+                | Orientation::Transpose
+                | Orientation::AntiTranspose
+        )
+    }
+
+    /// Dimensions of a `width` by `height` grid after reorienting it
+    #[inline]
+    pub fn size(self, [width, height]: [usize; 2]) -> [usize; 2] {
+        if self.swaps_axes() {
+            [height, width]
+        } else {
+            [width, height]
+        }
+    }
+
+    /// Position of the cell `[x, y]` of a grid with dimensions `size` after reorienting the grid
+    #[inline]
+    pub fn apply(self, [x, y]: [usize; 2], [width, height]: [usize; 2]) -> [usize; 2] {
+        match self {
+            Orientation::Identity => [x, y],
+            Orientation::Rotate90 => [height - 1 - y, x],
+            Orientation::Rotate180 => [width - 1 - x, height - 1 - y],
+            Orientation::Rotate270 => [y, width - 1 - x],
+            Orientation::FlipX => [width - 1 - x, y],
+            Orientation::FlipY => [x, height - 1 - y],
+            Orientation::Transpose => [y, x],
+            Orientation::AntiTranspose => [height - 1 - y, width - 1 - x],
+        }
+    }
+
+    /// Reorient an offset between cells
+    #[inline]
+    pub fn apply_offset(self, offset: IVec2) -> IVec2 {
+        let IVec2 { x, y } = offset;
+        match self {
+            Orientation::Identity => IVec2::new(x, y),
+            Orientation::Rotate90 => IVec2::new(-y, x),
+            Orientation::Rotate180 => IVec2::new(-x, -y),
+            Orientation::Rotate270 => IVec2::new(y, -x),
+            Orientation::FlipX => IVec2::new(-x, y),
+            Orientation::FlipY => IVec2::new(x, -y),
+            Orientation::Transpose => IVec2::new(y, x),
+            Orientation::AntiTranspose => IVec2::new(-y, -x),
+        }
+    }
+
+    /// The orientation that reorients by `self` and then by `next`
+    pub fn then(self, next: Orientation) -> Self {
+        let x = next.apply_offset(self.apply_offset(IVec2::X));
+        let y = next.apply_offset(self.apply_offset(IVec2::Y));
+        Orientation::ALL
+            .into_iter()
+            .find(|orientation| {
+                orientation.apply_offset(IVec2::X) == x && orientation.apply_offset(IVec2::Y) == y
+            })
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn orientations_compose() {
+        for a in Orientation::ALL {
+            for b in Orientation::ALL {
+                let offset = IVec2::new(2, 1);
+                assert_eq!(
+                    a.then(b).apply_offset(offset),
+                    b.apply_offset(a.apply_offset(offset))
+                );
+            }
+        }
+    }
+}
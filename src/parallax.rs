@@ -0,0 +1,245 @@
+use crate::geometry::TilemapGeometry;
+use crate::geometry::TilemapView;
+use bevy::math::vec2;
+use bevy::prelude::*;
+
+/// Moves a tilemap by a fraction of the motion of a camera, for parallax backgrounds.
+///
+/// The tilemap's transform is updated every frame, so it should not have a parent.
+/// Rotation of the tilemap is ignored.
+#[derive(Clone, Component, Debug)]
+pub struct TilemapParallax {
+    /// camera to follow, if `None` the tilemap follows the only `Camera2d`
+    pub camera: Option<Entity>,
+    /// fraction of the camera's motion the tilemap scrolls past by along each axis.
+    /// `1.0` scrolls with the rest of the world, `0.0` stays fixed to the camera.
+    pub factor: Vec2,
+    /// translation of the tilemap when the camera is at the origin.
+    /// When repeating, the centre of cell `[0, 0]` is placed here instead.
+    pub origin: Vec2,
+    /// Repeat the tilemap endlessly to cover the camera's view.
+    /// The tilemap's view is replaced with a wrapping [`TilemapView::Scroll`].
+    pub repeat: bool,
+}
+
+impl Default for TilemapParallax {
+    fn default() -> Self {
+        Self {
+            camera: None,
+            factor: Vec2::ONE,
+            origin: Vec2::ZERO,
+            repeat: false,
+        }
+    }
+}
+
+#[allow(clippy::type_complexity)]
+pub fn update_tilemap_parallax(
+    camera_query: Query<
+        (&Transform, Option<&OrthographicProjection>),
+        (With<Camera2d>, Without<TilemapParallax>),
+    >,
+    mut tilemap_query: Query<(
+        &TilemapParallax,
+        &TilemapGeometry,
+        &mut Transform,
+        &mut TilemapView,
+    )>,
+) {
+    for (parallax, geometry, mut transform, mut view) in tilemap_query.iter_mut() {
+        let camera = match parallax.camera {
+            Some(entity) => camera_query.get(entity).ok(),
+            None => camera_query.get_single().ok(),
+        };
+        let (camera_transform, projection) = match camera {
+            Some(camera) => camera,
+            None => continue,
+        };
+        let camera_position = camera_transform.translation.truncate();
+        if !parallax.repeat {
+            transform.translation = (parallax.origin + camera_position * (1. - parallax.factor))
+                .extend(transform.translation.z);
+            continue;
+        }
+        let projection = match projection {
+            Some(projection) => projection,
+            None => continue,
+        };
+        let step = geometry.cell_step() * transform.scale.truncate();
+        if step.x == 0. || step.y == 0. {
+            continue;
+        }
+        let view_size = projection.scale
+            * vec2(
+                projection.right - projection.left,
+                projection.top - projection.bottom,
+            );
+        let width = (view_size.x / step.x.abs()).ceil() as usize + 1;
+        let height = (view_size.y / step.y.abs()).ceil() as usize + 1;
+        let window_size = vec2(width as f32, height as f32);
+        let window_centre =
+            geometry.grid_origin([width, height]) + 0.5 * (window_size - 1.) * geometry.cell_step();
+        transform.translation = (camera_position - window_centre * transform.scale.truncate())
+            .extend(transform.translation.z);
+        let scroll = (camera_position * parallax.factor - parallax.origin) / step
+            + 0.5 * (Vec2::ONE - window_size);
+        *view = TilemapView::Scroll {
+            x: scroll.x,
+            y: scroll.y,
+            width,
+            height,
+            wrap: true,
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::math::vec3;
+
+    fn run_parallax(factor: Vec2, camera_position: Vec2) -> Vec3 {
+        let mut world = World::new();
+        world.spawn((
+            Camera2d::default(),
+            Transform::from_translation(camera_position.extend(999.)),
+        ));
+        let tilemap = world
+            .spawn((
+                TilemapParallax {
+                    factor,
+                    origin: Vec2::new(10., 20.),
+                    ..Default::default()
+                },
+                TilemapGeometry::default(),
+                TilemapView::All,
+                Transform::from_xyz(0., 0., 3.),
+            ))
+            .id();
+        let mut stage = SystemStage::single(update_tilemap_parallax);
+        stage.run(&mut world);
+        world.get::<Transform>(tilemap).unwrap().translation
+    }
+
+    #[test]
+    fn tilemap_follows_camera_by_factor() {
+        let camera = Vec2::new(100., -40.);
+        assert_eq!(run_parallax(Vec2::ONE, camera), Vec3::new(10., 20., 3.));
+        assert_eq!(run_parallax(Vec2::ZERO, camera), Vec3::new(110., -20., 3.));
+        assert_eq!(
+            run_parallax(Vec2::new(0.5, 0.25), camera),
+            Vec3::new(60., -10., 3.)
+        );
+    }
+
+    #[test]
+    fn tilemap_moves_with_camera() {
+        let mut world = World::new();
+        let camera = world
+            .spawn((Camera2d::default(), Transform::default()))
+            .id();
+        let tilemap = world
+            .spawn((
+                TilemapParallax {
+                    factor: Vec2::splat(0.5),
+                    ..Default::default()
+                },
+                TilemapGeometry::default(),
+                TilemapView::All,
+                Transform::default(),
+            ))
+            .id();
+        let mut stage = SystemStage::single(update_tilemap_parallax);
+        stage.run(&mut world);
+        assert_eq!(
+            world.get::<Transform>(tilemap).unwrap().translation,
+            Vec3::ZERO
+        );
+
+        world.get_mut::<Transform>(camera).unwrap().translation = Vec3::new(40., 80., 0.);
+        stage.run(&mut world);
+        assert_eq!(
+            world.get::<Transform>(tilemap).unwrap().translation,
+            Vec3::new(20., 40., 0.)
+        );
+    }
+
+    #[test]
+    fn repeating_tilemap_wraps_around_camera() {
+        let mut world = World::new();
+        let camera = world
+            .spawn((
+                Camera2d::default(),
+                OrthographicProjection {
+                    left: -40.,
+                    right: 40.,
+                    bottom: -24.,
+                    top: 24.,
+                    ..Default::default()
+                },
+                Transform::from_xyz(8., 4., 999.),
+            ))
+            .id();
+        let geometry = TilemapGeometry::default();
+        let tilemap = world
+            .spawn((
+                TilemapParallax {
+                    factor: Vec2::splat(0.5),
+                    origin: vec2(10., 20.),
+                    repeat: true,
+                    ..Default::default()
+                },
+                geometry.clone(),
+                TilemapView::All,
+                Transform::from_xyz(0., 0., 3.),
+            ))
+            .id();
+        let map_size = [4, 3];
+        let mut stage = SystemStage::single(update_tilemap_parallax);
+        let mut run = |world: &mut World, camera_position: Vec2| {
+            world.get_mut::<Transform>(camera).unwrap().translation = camera_position.extend(999.);
+            stage.run(world);
+            let transform = *world.get::<Transform>(tilemap).unwrap();
+            let view = world.get::<TilemapView>(tilemap).unwrap().clone();
+            (transform, view)
+        };
+        let pick = |point: Vec2, (transform, view): &(Transform, TilemapView)| {
+            crate::util::pick_tile(
+                point,
+                &GlobalTransform::from(*transform),
+                map_size[0],
+                map_size[1],
+                &geometry,
+                view,
+                None,
+            )
+        };
+
+        let near = run(&mut world, vec2(8., 4.));
+        assert!(matches!(
+            near.1,
+            TilemapView::Scroll {
+                width: 6,
+                height: 4,
+                wrap: true,
+                ..
+            }
+        ));
+        // the centre of cell [0, 0] is at the origin, moved by the parallax
+        assert_eq!(pick(vec2(14., 22.), &near), Some([0, 0]));
+
+        // two map widths and one map height away, scaled by the factor
+        let far = run(&mut world, vec2(8. + 256., 4. + 96.));
+        assert_eq!(far.0.translation, near.0.translation + vec3(256., 96., 0.));
+        assert_eq!(far.1.window(map_size), near.1.window(map_size));
+        match (&near.1, &far.1) {
+            (TilemapView::Scroll { x: a, y: b, .. }, TilemapView::Scroll { x: c, y: d, .. }) => {
+                assert_eq!([c - a, d - b], [8., 3.]);
+            }
+            _ => unreachable!(),
+        }
+        // the parallax point of cell [0, 0] is off camera, the repeat nearest the camera is drawn
+        assert_eq!(pick(vec2(142., 70.), &far), None);
+        assert_eq!(pick(vec2(142. + 128., 70. + 48.), &far), Some([0, 0]));
+    }
+}
@@ -0,0 +1,226 @@
+use crate::indexing::IndexableGrid;
+use crate::tile::Tileable;
+use crate::tilemap::Tilemap;
+
+/// A run of consecutive changed cells in row major order
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TileRun<T> {
+    /// index of the first cell of the run
+    pub start: usize,
+    /// the new tiles of the run's cells
+    pub tiles: Vec<T>,
+}
+
+/// The cells changed between two snapshots of a tilemap
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(try_from = "TilemapPatchData<T>")
+)]
+pub struct TilemapPatch<T> {
+    /// width of the tilemap after patching
+    pub width: usize,
+    /// height of the tilemap after patching
+    pub height: usize,
+    pub runs: Vec<TileRun<T>>,
+}
+
+/// A deserialized [`TilemapPatch`] with its dimensions and runs not yet checked
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct TilemapPatchData<T> {
+    width: usize,
+    height: usize,
+    runs: Vec<TileRun<T>>,
+}
+
+#[cfg(feature = "serde")]
+impl<T> TryFrom<TilemapPatchData<T>> for TilemapPatch<T> {
+    type Error = String;
+
+    fn try_from(data: TilemapPatchData<T>) -> Result<Self, Self::Error> {
+        let TilemapPatchData {
+            width,
+            height,
+            runs,
+        } = data;
+        let len = match width.checked_mul(height) {
+            Some(len) => len,
+            None => return Err(format!("a {width} by {height} tilemap is too large")),
+        };
+        for run in &runs {
+            match run.start.checked_add(run.tiles.len()) {
+                Some(end) if end <= len => {}
+                _ => {
+                    return Err(format!(
+                        "run of {} tiles from {} is outside of a {width} by {height} tilemap",
+                        run.tiles.len(),
+                        run.start
+                    ))
+                }
+            }
+        }
+        Ok(Self {
+            width,
+            height,
+            runs,
+        })
+    }
+}
+
+impl<T> TilemapPatch<T> {
+    /// True if applying the patch changes nothing
+    /// for a tilemap already of the patch's dimensions
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.runs.is_empty()
+    }
+
+    /// Number of changed cells
+    pub fn len(&self) -> usize {
+        self.runs.iter().map(|run| run.tiles.len()).sum()
+    }
+}
+
+impl<T> Tilemap<T>
+where
+    T: Tileable + PartialEq,
+{
+    /// The changes that turn this tilemap into `other`.
+    /// If the dimensions differ the cells added by resizing are compared as `T::default()`.
+    pub fn diff(&self, other: &Tilemap<T>) -> TilemapPatch<T> {
+        let default = T::default();
+        let mut runs: Vec<TileRun<T>> = vec![];
+        for (x, y, tile) in other.indexed_iter() {
+            let before = if x < self.width() && y < self.height() {
+                &self[[x, y]]
+            } else {
+                &default
+            };
+            if before == tile {
+                continue;
+            }
+            let index = other.index_grid(x, y);
+            match runs.last_mut() {
+                Some(run) if run.start + run.tiles.len() == index => run.tiles.push(tile.clone()),
+                _ => runs.push(TileRun {
+                    start: index,
+                    tiles: vec![tile.clone()],
+                }),
+            }
+        }
+        TilemapPatch {
+            width: other.width(),
+            height: other.height(),
+            runs,
+        }
+    }
+}
+
+impl<T> Tilemap<T>
+where
+    T: Tileable,
+{
+    /// Apply the changes of `patch`, resizing the tilemap to the patch's dimensions first.
+    /// Cells of runs past the end of the tilemap are ignored.
+    pub fn apply_patch(&mut self, patch: &TilemapPatch<T>) {
+        if self.width() != patch.width || self.height() != patch.height {
+            self.resize(patch.width, patch.height, T::default());
+        }
+        let len = self.width() * self.height();
+        for run in &patch.runs {
+            for (index, tile) in (run.start..len).zip(run.tiles.iter()) {
+                self[index] = tile.clone();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Clone, Debug, Default, PartialEq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    struct Cell(usize);
+
+    impl Tileable for Cell {}
+
+    fn assert_same(a: &Tilemap<Cell>, b: &Tilemap<Cell>) {
+        assert_eq!([a.width(), a.height()], [b.width(), b.height()]);
+        assert!(a.into_iter().eq(b.into_iter()));
+    }
+
+    #[test]
+    fn unchanged_tilemap_gives_empty_patch() {
+        let tilemap = Tilemap::from_fn(4, 3, |x, y| Cell(x + y));
+        let patch = tilemap.diff(&tilemap);
+        assert!(patch.is_empty());
+        assert_eq!(patch.len(), 0);
+    }
+
+    #[test]
+    fn changed_cells_are_grouped_in_runs() {
+        let before = Tilemap::<Cell>::from_default(5, 3);
+        let mut after = before.clone();
+        after[[1, 0]] = Cell(1);
+        after[[2, 0]] = Cell(2);
+        after[[4, 1]] = Cell(3);
+        after[[0, 2]] = Cell(4);
+        let patch = before.diff(&after);
+        assert_eq!(patch.runs.len(), 2);
+        assert_eq!(patch.runs[0].start, 1);
+        assert_eq!(patch.runs[1].tiles, vec![Cell(3), Cell(4)]);
+        assert_eq!(patch.len(), 4);
+
+        let mut patched = before.clone();
+        patched.apply_patch(&patch);
+        assert_same(&patched, &after);
+    }
+
+    #[test]
+    fn patch_resizes() {
+        let before = Tilemap::from_fn(3, 3, |x, y| Cell(1 + x + 3 * y));
+        for (width, height) in [(5, 2), (2, 4), (3, 3), (0, 0)] {
+            let mut after = before.clone();
+            after.resize(width, height, Cell(0));
+            if width > 0 && height > 0 {
+                after[[width - 1, height - 1]] = Cell(99);
+            }
+            let mut patched = before.clone();
+            patched.apply_patch(&before.diff(&after));
+            assert_same(&patched, &after);
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_round_trip() {
+        let before = Tilemap::<Cell>::from_default(4, 2);
+        let after = Tilemap::from_fn(3, 3, |x, y| Cell(x * y));
+        let patch = before.diff(&after);
+        let json = serde_json::to_string(&patch).unwrap();
+        assert_eq!(
+            serde_json::from_str::<TilemapPatch<Cell>>(&json).unwrap(),
+            patch
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn invalid_patches_are_rejected() {
+        let parse = |json: &str| serde_json::from_str::<TilemapPatch<Cell>>(json);
+        let json = format!(r#"{{"width":{},"height":2,"runs":[]}}"#, usize::MAX);
+        assert!(parse(&json).is_err());
+        assert!(parse(r#"{"width":2,"height":2,"runs":[{"start":3,"tiles":[1,2]}]}"#).is_err());
+        let json = format!(
+            r#"{{"width":2,"height":2,"runs":[{{"start":{},"tiles":[1]}}]}}"#,
+            usize::MAX
+        );
+        assert!(parse(&json).is_err());
+        assert!(parse(r#"{"width":2,"height":2,"runs":[{"start":2,"tiles":[1,2]}]}"#).is_ok());
+        assert!(parse(r#"{"width":0,"height":0,"runs":[]}"#).is_ok());
+    }
+}
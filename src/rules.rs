@@ -0,0 +1,568 @@
+use crate::compact::CompactTile;
+use crate::indexing::IndexableGrid;
+use crate::orientation::Orientation;
+use crate::tile::TextureAtlasTile;
+use crate::tile::Tileable;
+use crate::tilemap::Tilemap;
+use crate::variants::TileVariants;
+use bevy::math::ivec2;
+use bevy::prelude::*;
+
+/// What a neighbor of a cell must be for a [`TileRule`] to match
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum NeighborCondition {
+    /// anything
+    #[default]
+    Any,
+    /// a cell of the rule tile's terrain
+    This,
+    /// a cell of another terrain
+    NotThis,
+}
+
+impl NeighborCondition {
+    #[inline]
+    pub fn matches(self, is_this: bool) -> bool {
+        match self {
+            NeighborCondition::Any => true,
+            NeighborCondition::This => is_this,
+            NeighborCondition::NotThis => !is_this,
+        }
+    }
+}
+
+/// The tile placed by a [`TileRule`]
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum RuleOutput<T> {
+    Tile(T),
+    /// a variant picked by the cell's coordinates
+    Variants(TileVariants<T>),
+}
+
+impl<T> RuleOutput<T> {
+    #[inline]
+    pub fn pick(&self, seed: u64, cell: [usize; 2]) -> Option<&T> {
+        match self {
+            RuleOutput::Tile(tile) => Some(tile),
+            RuleOutput::Variants(variants) => variants.pick(seed, cell),
+        }
+    }
+}
+
+/// Tiles that can be reoriented, so rules matched rotated or mirrored place tiles turned to match
+pub trait Orientable: Tileable {
+    /// This tile reoriented by `orientation`
+    fn reoriented(&self, orientation: Orientation) -> Self;
+
+    /// True if tiles can be reoriented by `orientation`, rules aren't matched in other orientations
+    #[inline]
+    fn supports(_orientation: Orientation) -> bool {
+        true
+    }
+}
+
+impl Orientable for CompactTile {
+    #[inline]
+    fn reoriented(&self, orientation: Orientation) -> Self {
+        self.with_orientation(self.orientation().then(orientation))
+    }
+}
+
+/// Texture atlas tiles can only be flipped, so rules aren't matched in orientations that swap the axes
+impl Orientable for TextureAtlasTile {
+    #[inline]
+    fn supports(orientation: Orientation) -> bool {
+        !orientation.swaps_axes()
+    }
+
+    fn reoriented(&self, orientation: Orientation) -> Self {
+        let (flip_x, flip_y) = match orientation {
+            Orientation::FlipX => (true, false),
+            Orientation::FlipY => (false, true),
+            Orientation::Rotate180 => (true, true),
+            _ => (false, false),
+        };
+        Self {
+            flip_x: self.flip_x ^ flip_x,
+            flip_y: self.flip_y ^ flip_y,
+            ..self.clone()
+        }
+    }
+}
+
+/// A neighbor pattern and the tile placed at cells whose neighbors match it
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(try_from = "TileRuleData<T>")
+)]
+pub struct TileRule<T> {
+    /// side length of the square pattern, odd, with the cell itself at its centre
+    size: usize,
+    /// conditions row by row from the bottom left
+    conditions: Vec<NeighborCondition>,
+    /// also match the pattern rotated by quarter turns
+    pub rotate: bool,
+    /// also match the pattern mirrored left to right
+    pub mirror_x: bool,
+    /// also match the pattern mirrored top to bottom
+    pub mirror_y: bool,
+    pub output: RuleOutput<T>,
+}
+
+/// A deserialized [`TileRule`] with its pattern not yet checked to be square
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct TileRuleData<T> {
+    size: usize,
+    conditions: Vec<NeighborCondition>,
+    rotate: bool,
+    mirror_x: bool,
+    mirror_y: bool,
+    output: RuleOutput<T>,
+}
+
+#[cfg(feature = "serde")]
+impl<T> TryFrom<TileRuleData<T>> for TileRule<T> {
+    type Error = String;
+
+    fn try_from(data: TileRuleData<T>) -> Result<Self, Self::Error> {
+        let TileRuleData {
+            size,
+            conditions,
+            rotate,
+            mirror_x,
+            mirror_y,
+            output,
+        } = data;
+        if size % 2 == 0 {
+            return Err(format!("pattern size {size} isn't odd"));
+        }
+        if size.checked_mul(size) != Some(conditions.len()) {
+            return Err(format!(
+                "{} conditions don't fill a {size} by {size} pattern",
+                conditions.len()
+            ));
+        }
+        Ok(Self {
+            size,
+            conditions,
+            rotate,
+            mirror_x,
+            mirror_y,
+            output,
+        })
+    }
+}
+
+impl<T> TileRule<T> {
+    /// A rule with its pattern drawn as rows of text from top to bottom,
+    /// with `#` for [`NeighborCondition::This`], `.` for `NotThis` and any other character for `Any`.
+    /// The pattern is square with an odd side length, the centre character is ignored.
+    /// Returns `None` if the rows don't form such a square.
+    pub fn from_rows(rows: &[&str], output: RuleOutput<T>) -> Option<Self> {
+        let size = rows.len();
+        if size % 2 == 0 || rows.iter().any(|row| row.chars().count() != size) {
+            return None;
+        }
+        let conditions = rows
+            .iter()
+            .rev()
+            .flat_map(|row| row.chars())
+            .map(|c| match c {
+                '#' => NeighborCondition::This,
+                '.' => NeighborCondition::NotThis,
+                _ => NeighborCondition::Any,
+            })
+            .collect();
+        Some(Self {
+            size,
+            conditions,
+            rotate: false,
+            mirror_x: false,
+            mirror_y: false,
+            output,
+        })
+    }
+
+    #[inline]
+    pub fn with_rotation(mut self) -> Self {
+        self.rotate = true;
+        self
+    }
+
+    #[inline]
+    pub fn with_mirroring(mut self, mirror_x: bool, mirror_y: bool) -> Self {
+        self.mirror_x = mirror_x;
+        self.mirror_y = mirror_y;
+        self
+    }
+
+    /// Number of cells the pattern reaches from its centre
+    #[inline]
+    pub fn radius(&self) -> usize {
+        self.size / 2
+    }
+
+    /// The pattern's conditions with their offsets from the centre, leaving out the centre
+    fn offset_conditions(&self) -> impl Iterator<Item = (IVec2, NeighborCondition)> + '_ {
+        let radius = self.radius() as i32;
+        self.conditions
+            .iter()
+            .enumerate()
+            .map(move |(i, &condition)| {
+                let offset = ivec2((i % self.size) as i32, (i / self.size) as i32) - radius;
+                (offset, condition)
+            })
+            .filter(|&(offset, condition)| {
+                offset != IVec2::ZERO && condition != NeighborCondition::Any
+            })
+    }
+}
+
+impl<T> TileRule<T>
+where
+    T: Orientable,
+{
+    /// The orientations the pattern is matched in, in the order they are tried,
+    /// leaving out those the tiles can't be reoriented by
+    pub fn orientations(&self) -> impl Iterator<Item = Orientation> {
+        let orientations: &'static [Orientation] = match (self.rotate, self.mirror_x, self.mirror_y)
+        {
+            (true, false, false) => &Orientation::ROTATIONS,
+            (true, _, _) => &Orientation::ALL,
+            (false, false, false) => &[Orientation::Identity],
+            (false, true, false) => &[Orientation::Identity, Orientation::FlipX],
+            (false, false, true) => &[Orientation::Identity, Orientation::FlipY],
+            (false, true, true) => &[
+                Orientation::Identity,
+                Orientation::FlipX,
+                Orientation::FlipY,
+                Orientation::Rotate180,
+            ],
+        };
+        orientations
+            .iter()
+            .copied()
+            .filter(|&orientation| T::supports(orientation))
+    }
+
+    /// The orientation the pattern matches in around `cell`, given whether each cell is of this terrain
+    pub fn matches(&self, cell: IVec2, is_this: impl Fn(IVec2) -> bool) -> Option<Orientation> {
+        self.orientations().find(|&orientation| {
+            self.offset_conditions().all(|(offset, condition)| {
+                condition.matches(is_this(cell + orientation.apply_offset(offset)))
+            })
+        })
+    }
+}
+
+/// A tile chosen for a cell by the first of its rules matching the cell's neighbors.
+///
+/// Rules are only applied to cells of the rule tile's terrain,
+/// the cells of other terrains are left for their own rule tiles.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RuleTile<T> {
+    pub rules: Vec<TileRule<T>>,
+    /// placed if no rule matches
+    pub default: Option<RuleOutput<T>>,
+    /// treat cells outside of the tilemap as this terrain
+    pub outside_is_this: bool,
+}
+
+impl<T> Default for RuleTile<T> {
+    fn default() -> Self {
+        Self {
+            rules: vec![],
+            default: None,
+            outside_is_this: false,
+        }
+    }
+}
+
+impl<T> RuleTile<T>
+where
+    T: Orientable,
+{
+    pub fn new(rules: Vec<TileRule<T>>, default: Option<RuleOutput<T>>) -> Self {
+        Self {
+            rules,
+            default,
+            ..Default::default()
+        }
+    }
+
+    /// Number of cells around an edited cell whose tiles may change
+    pub fn radius(&self) -> usize {
+        self.rules.iter().map(TileRule::radius).max().unwrap_or(0)
+    }
+
+    /// The tile for `cell` of `source`, or `None` if the cell isn't of this terrain
+    /// or no rule matches it and there is no default
+    pub fn evaluate<S>(
+        &self,
+        source: &Tilemap<S>,
+        cell: [usize; 2],
+        is_this: impl Fn(&S) -> bool,
+        seed: u64,
+    ) -> Option<T>
+    where
+        S: Tileable,
+    {
+        let is_this_at = |point: IVec2| {
+            if point.x < 0 || point.y < 0 {
+                return self.outside_is_this;
+            }
+            match source.index_grid_checked(point.x as usize, point.y as usize) {
+                Some(index) => is_this(&source[index]),
+                None => self.outside_is_this,
+            }
+        };
+        let point = ivec2(cell[0] as i32, cell[1] as i32);
+        if !is_this_at(point) {
+            return None;
+        }
+        for rule in &self.rules {
+            if let Some(orientation) = rule.matches(point, is_this_at) {
+                return rule
+                    .output
+                    .pick(seed, cell)
+                    .map(|tile| tile.reoriented(orientation));
+            }
+        }
+        self.default
+            .as_ref()
+            .and_then(|default| default.pick(seed, cell))
+            .cloned()
+    }
+
+    /// Place the tile for each cell of this terrain in `source` at the same cell of `output`.
+    /// Cells of this terrain without a tile are set to the default tile.
+    pub fn apply<S>(
+        &self,
+        source: &Tilemap<S>,
+        output: &mut Tilemap<T>,
+        is_this: impl Fn(&S) -> bool,
+        seed: u64,
+    ) where
+        S: Tileable,
+    {
+        let width = source.width().min(output.width());
+        let height = source.height().min(output.height());
+        for y in 0..height {
+            for x in 0..width {
+                self.apply_cell(source, output, [x, y], &is_this, seed);
+            }
+        }
+    }
+
+    /// Update the cells of `output` whose tiles may have changed after editing the cells
+    /// `edited` of `source`. Edited cells no longer of this terrain are reset to the default tile,
+    /// so apply the rule tiles of their new terrains after this one.
+    pub fn apply_around<S>(
+        &self,
+        source: &Tilemap<S>,
+        output: &mut Tilemap<T>,
+        edited: impl IntoIterator<Item = [usize; 2]>,
+        is_this: impl Fn(&S) -> bool,
+        seed: u64,
+    ) where
+        S: Tileable,
+    {
+        let radius = self.radius();
+        let width = source.width().min(output.width());
+        let height = source.height().min(output.height());
+        let edited: Vec<[usize; 2]> = edited.into_iter().collect();
+        for &[x, y] in &edited {
+            if x < width && y < height && !is_this(&source[[x, y]]) {
+                output[[x, y]] = T::default();
+            }
+        }
+        let mut cells: Vec<[usize; 2]> = edited
+            .iter()
+            .filter(|&&[x, y]| x < width && y < height)
+            .flat_map(|&[x, y]| {
+                let xs = x.saturating_sub(radius)..(x + radius + 1).min(width);
+                let ys = y.saturating_sub(radius)..(y + radius + 1).min(height);
+                ys.flat_map(move |y| xs.clone().map(move |x| [x, y]))
+            })
+            .collect();
+        cells.sort_unstable_by_key(|&[x, y]| (y, x));
+        cells.dedup();
+        for cell in cells {
+            self.apply_cell(source, output, cell, &is_this, seed);
+        }
+    }
+
+    fn apply_cell<S>(
+        &self,
+        source: &Tilemap<S>,
+        output: &mut Tilemap<T>,
+        cell: [usize; 2],
+        is_this: &impl Fn(&S) -> bool,
+        seed: u64,
+    ) where
+        S: Tileable,
+    {
+        if !is_this(&source[cell]) {
+            return;
+        }
+        output[cell] = self
+            .evaluate(source, cell, is_this, seed)
+            .unwrap_or_default();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Clone, Debug, Default, PartialEq)]
+    struct Cell(bool);
+
+    impl Tileable for Cell {}
+
+    fn wall_tiles() -> RuleTile<CompactTile> {
+        RuleTile::new(
+            vec![
+                // wall end, open on three sides
+                TileRule::from_rows(
+                    &[
+                        "?.?", //
+                        ".#.", //
+                        "?#?", //
+                    ],
+                    RuleOutput::Tile(CompactTile::new(1)),
+                )
+                .unwrap()
+                .with_rotation(),
+                // straight wall
+                TileRule::from_rows(
+                    &[
+                        "?#?", //
+                        ".#.", //
+                        "?#?", //
+                    ],
+                    RuleOutput::Tile(CompactTile::new(2)),
+                )
+                .unwrap()
+                .with_rotation(),
+            ],
+            Some(RuleOutput::Tile(CompactTile::new(9))),
+        )
+    }
+
+    fn walls(rows: &[&str]) -> Tilemap<Cell> {
+        let height = rows.len();
+        Tilemap::from_fn(rows[0].len(), height, |x, y| {
+            Cell(rows[height - 1 - y].as_bytes()[x] == b'#')
+        })
+    }
+
+    #[test]
+    fn rules_match_rotated_patterns() {
+        let source = walls(&[
+            "#....", //
+            "#....", //
+            "###..", //
+        ]);
+        let rule_tile = wall_tiles();
+        let tile = |cell| rule_tile.evaluate(&source, cell, |cell| cell.0, 0);
+        assert_eq!(tile([0, 2]), Some(CompactTile::new(1)));
+        assert_eq!(tile([0, 1]), Some(CompactTile::new(2)));
+        assert_eq!(
+            tile([2, 0]),
+            Some(CompactTile::new(1).with_orientation(Orientation::Rotate270))
+        );
+        assert_eq!(tile([0, 0]), Some(CompactTile::new(9)));
+        assert_eq!(tile([3, 0]), None);
+    }
+
+    #[test]
+    fn edits_are_applied_incrementally() {
+        let mut source = walls(&[
+            ".....", //
+            ".###.", //
+            ".....", //
+        ]);
+        let rule_tile = wall_tiles();
+        let mut output = Tilemap::<CompactTile>::from_default(5, 3);
+        rule_tile.apply(&source, &mut output, |cell| cell.0, 0);
+        assert_eq!(output[[2, 1]].index, 2);
+
+        source[[2, 1]] = Cell(false);
+        source[[4, 2]] = Cell(true);
+        // edits off the map are ignored
+        let edited = [[2, 1], [4, 2], [usize::MAX, 0], [7, usize::MAX]];
+        rule_tile.apply_around(&source, &mut output, edited, |cell| cell.0, 0);
+        let mut expected = Tilemap::<CompactTile>::from_default(5, 3);
+        rule_tile.apply(&source, &mut expected, |cell| cell.0, 0);
+        assert!((&output).into_iter().eq(&expected));
+        assert_eq!(output[[1, 1]].index, 9);
+        assert_eq!(output[[2, 1]], CompactTile::default());
+    }
+
+    #[test]
+    fn atlas_tiles_are_only_matched_flipped() {
+        let source = walls(&[
+            "#....", //
+            "#....", //
+            "###..", //
+        ]);
+        let rule_tile = RuleTile::new(
+            vec![TileRule::from_rows(
+                &[
+                    "?.?", //
+                    ".#.", //
+                    "?#?", //
+                ],
+                RuleOutput::Tile(TextureAtlasTile::new(1)),
+            )
+            .unwrap()
+            .with_rotation()
+            .with_mirroring(true, true)],
+            Some(RuleOutput::Tile(TextureAtlasTile::new(9))),
+        );
+        assert!(rule_tile.rules[0]
+            .orientations()
+            .all(|orientation| !orientation.swaps_axes()));
+        let tile = |cell| rule_tile.evaluate(&source, cell, |cell| cell.0, 0);
+        assert_eq!(tile([0, 2]), Some(TextureAtlasTile::new(1)));
+        // the end of the horizontal wall only matches turned a quarter
+        assert_eq!(tile([2, 0]), Some(TextureAtlasTile::new(9)));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn misshapen_patterns_are_rejected() {
+        let rule = TileRule::from_rows(
+            &[
+                "?.?", //
+                ".#.", //
+                "?#?", //
+            ],
+            RuleOutput::Tile(CompactTile::new(1)),
+        )
+        .unwrap()
+        .with_rotation();
+        let json = serde_json::to_string(&rule).unwrap();
+        assert_eq!(
+            serde_json::from_str::<TileRule<CompactTile>>(&json).unwrap(),
+            rule
+        );
+
+        let mut value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        value["size"] = 2.into();
+        assert!(serde_json::from_value::<TileRule<CompactTile>>(value.clone()).is_err());
+        value["size"] = 0.into();
+        assert!(serde_json::from_value::<TileRule<CompactTile>>(value.clone()).is_err());
+        value["size"] = 5.into();
+        assert!(serde_json::from_value::<TileRule<CompactTile>>(value.clone()).is_err());
+        value["size"] = u64::MAX.into();
+        assert!(serde_json::from_value::<TileRule<CompactTile>>(value).is_err());
+    }
+}
@@ -0,0 +1,296 @@
+use crate::cell_sizes::TilemapCellSizes;
+use crate::extraction::camera_area;
+use crate::extraction::extract_visible_atlas_tilemaps;
+use crate::extraction::extract_visible_tilemaps;
+use crate::extraction::tilemap_aabb;
+use crate::extraction::ExtractableAtlasTilemap;
+use crate::extraction::ExtractableTilemap;
+use crate::extraction::TilemapExtractionSettings;
+use crate::geometry::TilemapGeometry;
+use crate::geometry::TilemapView;
+use crate::indexing::IndexableGrid;
+use bevy::prelude::*;
+use bevy::render::primitives::Aabb;
+use bevy::render::view::RenderLayers;
+use bevy::render::Extract;
+use bevy::sprite::ExtractedSprites;
+
+/// Draws the tiles of the tilemap on another entity, with this entity's own
+/// geometry, view, transform and visibility.
+///
+/// Give each view entity different [`RenderLayers`]
+/// to draw different regions of one tilemap for different cameras,
+/// for split-screen or minimaps, without copying the tilemap.
+/// Each view only draws the cells seen by the cameras sharing one of its render layers.
+/// Tilemaps drawn with a texture atlas use the atlas of the source entity.
+///
+/// Only sources drawn by an [`ExtractAtlasTilemapPlugin`](crate::extraction::ExtractAtlasTilemapPlugin)
+/// or [`ExtractTilemapPlugin`](crate::extraction::ExtractTilemapPlugin) are shared,
+/// such as [`Tilemap`](crate::tilemap::Tilemap)s. Compact and hash tilemaps can't be shared.
+#[derive(Clone, Copy, Component, Debug, PartialEq, Eq)]
+pub struct TilemapSource {
+    /// the entity with the tilemap
+    pub tilemap: Entity,
+}
+
+impl TilemapSource {
+    pub fn new(tilemap: Entity) -> Self {
+        Self { tilemap }
+    }
+}
+
+/// An entity drawing another entity's tilemap
+#[derive(Bundle)]
+pub struct TilemapViewBundle {
+    pub source: TilemapSource,
+    pub geometry: TilemapGeometry,
+    pub view: TilemapView,
+    pub transform: Transform,
+    pub global_transform: GlobalTransform,
+    pub visibility: Visibility,
+    pub computed_visibility: ComputedVisibility,
+}
+
+impl TilemapViewBundle {
+    pub fn new(tilemap: Entity) -> Self {
+        Self {
+            source: TilemapSource::new(tilemap),
+            geometry: Default::default(),
+            view: Default::default(),
+            transform: Default::default(),
+            global_transform: Default::default(),
+            visibility: Default::default(),
+            computed_visibility: Default::default(),
+        }
+    }
+}
+
+/// Corners of the area of the world seen by each active camera, with the camera's layers
+fn layered_camera_areas<'a>(
+    cameras: impl Iterator<Item = (&'a Camera, &'a GlobalTransform, Option<&'a RenderLayers>)>,
+) -> Vec<([Vec2; 4], RenderLayers)> {
+    cameras
+        .filter(|(camera, ..)| camera.is_active)
+        .map(|(camera, camera_transform, layers)| {
+            (
+                camera_area(camera, camera_transform),
+                layers.copied().unwrap_or_default(),
+            )
+        })
+        .collect()
+}
+
+/// Replaces `areas` with the areas of the cameras sharing a layer with `layers`
+fn areas_seen_by(
+    cameras: &[([Vec2; 4], RenderLayers)],
+    layers: RenderLayers,
+    areas: &mut Vec<[Vec2; 4]>,
+) {
+    areas.clear();
+    areas.extend(
+        cameras
+            .iter()
+            .filter(|(_, camera_layers)| camera_layers.intersects(&layers))
+            .map(|(area, _)| *area),
+    );
+}
+
+#[allow(clippy::type_complexity)]
+pub fn extract_shared_atlas_tilemap<T>(
+    mut extracted_sprites: ResMut<ExtractedSprites>,
+    settings: Extract<Res<TilemapExtractionSettings>>,
+    texture_atlases: Extract<Res<Assets<TextureAtlas>>>,
+    camera_query: Extract<Query<(&Camera, &GlobalTransform, Option<&RenderLayers>)>>,
+    source_query: Extract<Query<(&T, &Handle<TextureAtlas>)>>,
+    view_query: Extract<
+        Query<(
+            Entity,
+            &TilemapSource,
+            &TilemapGeometry,
+            &TilemapView,
+            Option<&TilemapCellSizes>,
+            &GlobalTransform,
+            &ComputedVisibility,
+            Option<&RenderLayers>,
+        )>,
+    >,
+) where
+    T: ExtractableAtlasTilemap,
+{
+    let cameras = layered_camera_areas(camera_query.iter());
+    let mut areas = Vec::with_capacity(cameras.len());
+    for (entity, source, geometry, view, cell_sizes, transform, visibility, layers) in
+        view_query.iter()
+    {
+        let (tilemap, texture_atlas_handle) = match source_query.get(source.tilemap) {
+            Ok(source) => source,
+            Err(_) => continue,
+        };
+        let texture_atlas = match texture_atlases.get(texture_atlas_handle) {
+            Some(texture_atlas) => texture_atlas,
+            None => continue,
+        };
+        areas_seen_by(&cameras, layers.copied().unwrap_or_default(), &mut areas);
+        extract_visible_atlas_tilemaps(
+            &mut extracted_sprites.sprites,
+            &areas,
+            &settings,
+            std::iter::once((
+                entity,
+                tilemap,
+                geometry,
+                view,
+                cell_sizes,
+                texture_atlas,
+                transform,
+                visibility.is_visible(),
+            )),
+        );
+    }
+}
+
+#[allow(clippy::type_complexity)]
+pub fn extract_shared_tilemap<T>(
+    mut extracted_sprites: ResMut<ExtractedSprites>,
+    settings: Extract<Res<TilemapExtractionSettings>>,
+    images: Extract<Res<Assets<Image>>>,
+    camera_query: Extract<Query<(&Camera, &GlobalTransform, Option<&RenderLayers>)>>,
+    source_query: Extract<Query<&T>>,
+    view_query: Extract<
+        Query<(
+            Entity,
+            &TilemapSource,
+            &TilemapGeometry,
+            &TilemapView,
+            Option<&TilemapCellSizes>,
+            &GlobalTransform,
+            &ComputedVisibility,
+            Option<&RenderLayers>,
+        )>,
+    >,
+) where
+    T: ExtractableTilemap,
+{
+    let cameras = layered_camera_areas(camera_query.iter());
+    let mut areas = Vec::with_capacity(cameras.len());
+    for (entity, source, geometry, view, cell_sizes, transform, visibility, layers) in
+        view_query.iter()
+    {
+        let tilemap = match source_query.get(source.tilemap) {
+            Ok(tilemap) => tilemap,
+            Err(_) => continue,
+        };
+        areas_seen_by(&cameras, layers.copied().unwrap_or_default(), &mut areas);
+        extract_visible_tilemaps(
+            &mut extracted_sprites.sprites,
+            &areas,
+            &settings,
+            Some(&images),
+            std::iter::once((
+                entity,
+                tilemap,
+                geometry,
+                view,
+                cell_sizes,
+                transform,
+                visibility.is_visible(),
+            )),
+        );
+    }
+}
+
+/// Keeps the [`Aabb`] of each entity drawing a `T` tilemap of another entity up to date
+#[allow(clippy::type_complexity)]
+pub fn calculate_shared_tilemap_bounds<T>(
+    mut commands: Commands,
+    source_query: Query<&T>,
+    mut view_query: Query<(
+        Entity,
+        &TilemapSource,
+        &TilemapGeometry,
+        &TilemapView,
+        Option<&TilemapCellSizes>,
+        Option<&mut Aabb>,
+    )>,
+) where
+    T: Component + IndexableGrid,
+{
+    for (entity, source, geometry, view, cell_sizes, aabb) in view_query.iter_mut() {
+        let tilemap = match source_query.get(source.tilemap) {
+            Ok(tilemap) => tilemap,
+            Err(_) => continue,
+        };
+        let bounds = tilemap_aabb(
+            [tilemap.width(), tilemap.height()],
+            geometry,
+            view,
+            cell_sizes,
+        );
+        match aabb {
+            Some(mut aabb) => {
+                if aabb.center != bounds.center || aabb.half_extents != bounds.half_extents {
+                    *aabb = bounds;
+                }
+            }
+            None => {
+                commands.entity(entity).insert(bounds);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tile::TextureAtlasTile;
+    use crate::tilemap::Tilemap;
+    use bevy::math::vec2;
+
+    #[test]
+    fn views_are_culled_by_the_cameras_sharing_their_layers() {
+        let area = |x: f32| [vec2(x, 0.), vec2(x + 1., 0.), vec2(x + 1., 1.), vec2(x, 1.)];
+        let cameras = [
+            (area(0.), RenderLayers::default()),
+            (area(10.), RenderLayers::layer(1)),
+            (area(20.), RenderLayers::layer(2)),
+            (area(30.), RenderLayers::layer(1).with(2)),
+        ];
+        let mut areas = vec![area(-10.)];
+        areas_seen_by(&cameras, RenderLayers::default(), &mut areas);
+        assert_eq!(areas, vec![area(0.)]);
+        areas_seen_by(&cameras, RenderLayers::layer(1), &mut areas);
+        assert_eq!(areas, vec![area(10.), area(30.)]);
+        areas_seen_by(&cameras, RenderLayers::layer(2), &mut areas);
+        assert_eq!(areas, vec![area(20.), area(30.)]);
+        areas_seen_by(&cameras, RenderLayers::layer(3), &mut areas);
+        assert!(areas.is_empty());
+    }
+
+    #[test]
+    fn views_are_bounded_by_their_own_sections() {
+        let mut world = World::new();
+        let tilemap = world
+            .spawn(Tilemap::<TextureAtlasTile>::from_default(8, 8))
+            .id();
+        let section = TilemapView::Section {
+            x: 2,
+            y: 2,
+            width: 2,
+            height: 3,
+        };
+        let view = world
+            .spawn(TilemapViewBundle {
+                view: section.clone(),
+                ..TilemapViewBundle::new(tilemap)
+            })
+            .id();
+        let mut stage =
+            SystemStage::single(calculate_shared_tilemap_bounds::<Tilemap<TextureAtlasTile>>);
+        stage.run(&mut world);
+        let aabb = world.get::<Aabb>(view).unwrap();
+        let expected = tilemap_aabb([8, 8], &TilemapGeometry::default(), &section, None);
+        assert_eq!(aabb.center, expected.center);
+        assert_eq!(aabb.half_extents, expected.half_extents);
+        assert!(world.get::<Aabb>(tilemap).is_none());
+    }
+}
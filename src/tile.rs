@@ -7,7 +7,8 @@ impl<T> Tileable for Option<T> where T: Tileable {}
 impl Tileable for TextureAtlasTile {}
 impl Tileable for SpriteTile {}
 
-#[derive(Component, Debug, Default, Clone, Reflect)]
+#[derive(Component, Debug, Clone, Reflect)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct TextureAtlasTile {
     /// index of the image in the texture atlas
     pub index: usize,
@@ -20,7 +21,40 @@ pub struct TextureAtlasTile {
     /// The size of the tile in the grid
     pub custom_size: Option<Vec2>,
     /// [`Anchor`] point of the sprite in the world
+    #[cfg_attr(feature = "serde", serde(with = "anchor_serde"))]
     pub anchor: Anchor,
+    /// Translation of the tile from the centre of its cell, in grid space
+    pub offset: Vec2,
+    /// Scale of the tile relative to its cell
+    pub scale: Vec2,
+}
+
+impl Default for TextureAtlasTile {
+    fn default() -> Self {
+        Self {
+            index: 0,
+            color: Color::default(),
+            flip_x: false,
+            flip_y: false,
+            custom_size: None,
+            anchor: Anchor::default(),
+            offset: Vec2::ZERO,
+            scale: Vec2::ONE,
+        }
+    }
+}
+
+impl PartialEq for TextureAtlasTile {
+    fn eq(&self, other: &Self) -> bool {
+        self.index == other.index
+            && self.color == other.color
+            && self.flip_x == other.flip_x
+            && self.flip_y == other.flip_y
+            && self.custom_size == other.custom_size
+            && self.anchor.as_vec() == other.anchor.as_vec()
+            && self.offset == other.offset
+            && self.scale == other.scale
+    }
 }
 
 impl TextureAtlasTile {
@@ -32,7 +66,7 @@ impl TextureAtlasTile {
     }
 }
 
-#[derive(Component, Debug, Default, Clone, Reflect)]
+#[derive(Component, Debug, Clone, Reflect)]
 pub struct SpriteTile {
     /// Asset handle for the tile's texture
     pub texture: Handle<Image>,
@@ -46,6 +80,38 @@ pub struct SpriteTile {
     pub custom_size: Option<Vec2>,
     /// [`Anchor`] point of the sprite in the world
     pub anchor: Anchor,
+    /// Translation of the tile from the centre of its cell, in grid space
+    pub offset: Vec2,
+    /// Scale of the tile relative to its cell
+    pub scale: Vec2,
+}
+
+impl Default for SpriteTile {
+    fn default() -> Self {
+        Self {
+            texture: Handle::default(),
+            color: Color::default(),
+            flip_x: false,
+            flip_y: false,
+            custom_size: None,
+            anchor: Anchor::default(),
+            offset: Vec2::ZERO,
+            scale: Vec2::ONE,
+        }
+    }
+}
+
+impl PartialEq for SpriteTile {
+    fn eq(&self, other: &Self) -> bool {
+        self.texture == other.texture
+            && self.color == other.color
+            && self.flip_x == other.flip_x
+            && self.flip_y == other.flip_y
+            && self.custom_size == other.custom_size
+            && self.anchor.as_vec() == other.anchor.as_vec()
+            && self.offset == other.offset
+            && self.scale == other.scale
+    }
 }
 
 impl SpriteTile {
@@ -56,3 +122,94 @@ impl SpriteTile {
         }
     }
 }
+
+/// Composes a tile's `offset` and `scale` onto the transform of its cell.
+/// Only the drawn sprite moves, the tile still occupies its cell for picking.
+#[inline]
+pub(crate) fn offset_transform(
+    cell: GlobalTransform,
+    offset: Vec2,
+    scale: Vec2,
+) -> GlobalTransform {
+    if offset == Vec2::ZERO && scale == Vec2::ONE {
+        cell
+    } else {
+        cell.mul_transform(Transform {
+            translation: offset.extend(0.),
+            scale: scale.extend(1.),
+            ..Default::default()
+        })
+    }
+}
+
+/// Serializes an [`Anchor`] as its position
+#[cfg(feature = "serde")]
+mod anchor_serde {
+    use bevy::math::vec2;
+    use bevy::prelude::*;
+    use bevy::sprite::Anchor;
+    use serde::Deserialize;
+    use serde::Deserializer;
+    use serde::Serialize;
+    use serde::Serializer;
+
+    pub fn serialize<S: Serializer>(anchor: &Anchor, serializer: S) -> Result<S::Ok, S::Error> {
+        anchor.as_vec().serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Anchor, D::Error> {
+        let point = Vec2::deserialize(deserializer)?;
+        Ok([
+            Anchor::Center,
+            Anchor::BottomLeft,
+            Anchor::BottomCenter,
+            Anchor::BottomRight,
+            Anchor::CenterLeft,
+            Anchor::CenterRight,
+            Anchor::TopLeft,
+            Anchor::TopCenter,
+            Anchor::TopRight,
+        ]
+        .into_iter()
+        .find(|anchor| anchor.as_vec() == point)
+        .unwrap_or(Anchor::Custom(vec2(point.x, point.y))))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::math::vec2;
+
+    #[test]
+    fn defaults_leave_tiles_in_their_cells() {
+        let tile = TextureAtlasTile::default();
+        assert_eq!([tile.offset, tile.scale], [Vec2::ZERO, Vec2::ONE]);
+        assert_eq!(tile, TextureAtlasTile::new(0));
+        let tile = SpriteTile::default();
+        assert_eq!([tile.offset, tile.scale], [Vec2::ZERO, Vec2::ONE]);
+
+        let cell = GlobalTransform::from_xyz(1., 2., 3.);
+        assert_eq!(offset_transform(cell, Vec2::ZERO, Vec2::ONE), cell);
+    }
+
+    #[test]
+    fn tiles_compare_anchors_offsets_and_scales() {
+        let tile = TextureAtlasTile::new(3);
+        let custom_centre = TextureAtlasTile {
+            anchor: Anchor::Custom(Vec2::ZERO),
+            ..tile.clone()
+        };
+        assert_eq!(tile, custom_centre);
+        let offset = TextureAtlasTile {
+            offset: vec2(0.5, 0.),
+            ..tile.clone()
+        };
+        assert_ne!(tile, offset);
+        let scaled = SpriteTile {
+            scale: vec2(2., 1.),
+            ..Default::default()
+        };
+        assert_ne!(SpriteTile::default(), scaled);
+    }
+}
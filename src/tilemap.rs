@@ -3,6 +3,11 @@ use crate::tile::Tileable;
 use bevy::prelude::*;
 
 #[derive(Clone, Component, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(try_from = "TilemapData<T>")
+)]
 pub struct Tilemap<T>
 where
     T: Tileable,
@@ -15,6 +20,42 @@ where
     height: usize,
 }
 
+/// A deserialized [`Tilemap`] with dimensions not yet checked against its tiles
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct TilemapData<T> {
+    tiles: Vec<T>,
+    width: usize,
+    height: usize,
+}
+
+#[cfg(feature = "serde")]
+impl<T> TryFrom<TilemapData<T>> for Tilemap<T>
+where
+    T: Tileable,
+{
+    type Error = String;
+
+    fn try_from(data: TilemapData<T>) -> Result<Self, Self::Error> {
+        let TilemapData {
+            tiles,
+            width,
+            height,
+        } = data;
+        if width.checked_mul(height) != Some(tiles.len()) {
+            return Err(format!(
+                "{} tiles don't fill a {width} by {height} tilemap",
+                tiles.len()
+            ));
+        }
+        Ok(Self {
+            tiles,
+            width,
+            height,
+        })
+    }
+}
+
 impl<T> IndexableGrid for Tilemap<T>
 where
     T: Tileable,
@@ -74,6 +115,45 @@ where
     pub fn from_default(width: usize, height: usize) -> Self {
         Self::from_elem(width, height, T::default())
     }
+
+    /// Change the dimensions of the tilemap.
+    /// Tiles keep their coordinates and new cells are set to `fill`.
+    pub fn resize(&mut self, width: usize, height: usize, fill: T) {
+        let mut tiles = std::mem::take(&mut self.tiles);
+        let old_width = self.width;
+        let old_height = self.height;
+        tiles.truncate(old_width * old_height.min(height));
+        let mut rows = tiles.into_iter();
+        self.tiles = Vec::with_capacity(width * height);
+        for y in 0..height {
+            for x in 0..width.max(old_width) {
+                let tile = if y < old_height && x < old_width {
+                    rows.next()
+                } else {
+                    None
+                };
+                if x < width {
+                    self.tiles.push(tile.unwrap_or_else(|| fill.clone()));
+                }
+            }
+        }
+        self.width = width;
+        self.height = height;
+    }
+
+    /// The tiles of row `y`
+    #[inline]
+    pub fn row(&self, y: usize) -> &[T] {
+        let start = self.index_grid(0, y);
+        &self.tiles[start..start + self.width]
+    }
+
+    /// The tiles of row `y`
+    #[inline]
+    pub fn row_mut(&mut self, y: usize) -> &mut [T] {
+        let start = self.index_grid(0, y);
+        &mut self.tiles[start..start + self.width]
+    }
 }
 
 impl<U> Tilemap<Option<U>>
@@ -203,3 +283,73 @@ where
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Clone, Debug, Default, PartialEq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    struct Cell(u32);
+
+    impl Tileable for Cell {}
+
+    fn numbered(width: usize, height: usize) -> Tilemap<Cell> {
+        Tilemap::from_fn(width, height, |x, y| Cell(10 * y as u32 + x as u32 + 1))
+    }
+
+    fn cells(tilemap: &Tilemap<Cell>) -> Vec<u32> {
+        tilemap.into_iter().map(|cell| cell.0).collect()
+    }
+
+    #[test]
+    fn resize_grow() {
+        let mut tilemap = numbered(2, 2);
+        tilemap.resize(3, 3, Cell(0));
+        assert_eq!([tilemap.width(), tilemap.height()], [3, 3]);
+        assert_eq!(cells(&tilemap), vec![1, 2, 0, 11, 12, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn resize_shrink() {
+        let mut tilemap = numbered(3, 3);
+        tilemap.resize(2, 1, Cell(0));
+        assert_eq!([tilemap.width(), tilemap.height()], [2, 1]);
+        assert_eq!(cells(&tilemap), vec![1, 2]);
+    }
+
+    #[test]
+    fn resize_grow_and_shrink() {
+        let mut tilemap = numbered(3, 2);
+        tilemap.resize(2, 3, Cell(0));
+        assert_eq!(cells(&tilemap), vec![1, 2, 11, 12, 0, 0]);
+
+        let mut tilemap = numbered(2, 3);
+        tilemap.resize(3, 2, Cell(0));
+        assert_eq!(cells(&tilemap), vec![1, 2, 0, 11, 12, 0]);
+
+        tilemap.resize(0, 0, Cell(0));
+        assert!(cells(&tilemap).is_empty());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_round_trip() {
+        let tilemap = numbered(3, 2);
+        let json = serde_json::to_string(&tilemap).unwrap();
+        let deserialized: Tilemap<Cell> = serde_json::from_str(&json).unwrap();
+        assert_eq!([deserialized.width(), deserialized.height()], [3, 2]);
+        assert_eq!(cells(&deserialized), cells(&tilemap));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn mismatched_dimensions_are_rejected() {
+        let json = r#"{"tiles":[1,2,3],"width":2,"height":2}"#;
+        assert!(serde_json::from_str::<Tilemap<Cell>>(json).is_err());
+        let json = format!(r#"{{"tiles":[],"width":{},"height":2}}"#, usize::MAX);
+        assert!(serde_json::from_str::<Tilemap<Cell>>(&json).is_err());
+        let json = r#"{"tiles":[1,2,3,4],"width":2,"height":2}"#;
+        assert!(serde_json::from_str::<Tilemap<Cell>>(json).is_ok());
+    }
+}
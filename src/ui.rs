@@ -0,0 +1,266 @@
+use crate::geometry::TilemapView;
+use crate::indexing::IndexableGrid;
+use crate::tile::TextureAtlasTile;
+use crate::tilemap::Tilemap;
+use bevy::math::vec2;
+use bevy::prelude::*;
+use bevy::render::Extract;
+use bevy::render::RenderApp;
+use bevy::render::RenderStage;
+use bevy::ui::ExtractedUiNode;
+use bevy::ui::ExtractedUiNodes;
+use bevy::ui::FocusPolicy;
+use bevy::ui::RenderUiSystem;
+use bevy::ui::UiStack;
+
+/// How the cells of a UI tilemap are sized to fill its node
+#[derive(Clone, Copy, Component, Debug, Default, PartialEq, Eq, Reflect)]
+#[reflect(Component)]
+pub enum UiTilemapScaling {
+    /// Square cells as large as fit within the node, with the grid centred in the node
+    #[default]
+    Fit,
+    /// Stretch the cells to fill the node exactly
+    Stretch,
+}
+
+impl UiTilemapScaling {
+    /// The size of each cell and the position of the bottom left corner of the grid
+    /// relative to the centre of a node of size `node_size`, for a grid of dimensions `layout`
+    pub fn cell_layout(self, node_size: Vec2, layout: [usize; 2]) -> (Vec2, Vec2) {
+        let cells = vec2(layout[0].max(1) as f32, layout[1].max(1) as f32);
+        let cell_size = match self {
+            UiTilemapScaling::Fit => Vec2::splat((node_size / cells).min_element()),
+            UiTilemapScaling::Stretch => node_size / cells,
+        };
+        (cell_size, -0.5 * cell_size * cells)
+    }
+}
+
+/// A tilemap drawn inside a UI node, scaled to the node's size.
+///
+/// The tiles' color is drawn, their flips, size, anchor, offset and scale are ignored.
+/// UI nodes can't be drawn mirrored, so flipped tiles are drawn unflipped.
+#[derive(Bundle, Default)]
+pub struct UiTilemapBundle {
+    pub node: Node,
+    pub style: Style,
+    pub tilemap: Tilemap<TextureAtlasTile>,
+    pub view: TilemapView,
+    pub scaling: UiTilemapScaling,
+    pub texture_atlas: Handle<TextureAtlas>,
+    pub focus_policy: FocusPolicy,
+    pub transform: Transform,
+    pub global_transform: GlobalTransform,
+    pub visibility: Visibility,
+    pub computed_visibility: ComputedVisibility,
+    pub z_index: ZIndex,
+}
+
+#[allow(clippy::type_complexity)]
+pub fn extract_ui_tilemaps(
+    extracted_uinodes: Option<ResMut<ExtractedUiNodes>>,
+    ui_stack: Extract<Option<Res<UiStack>>>,
+    texture_atlases: Extract<Res<Assets<TextureAtlas>>>,
+    tilemap_query: Extract<
+        Query<(
+            &Node,
+            &Tilemap<TextureAtlasTile>,
+            &TilemapView,
+            &UiTilemapScaling,
+            &Handle<TextureAtlas>,
+            &GlobalTransform,
+            &ComputedVisibility,
+            Option<&CalculatedClip>,
+        )>,
+    >,
+) {
+    let (mut extracted_uinodes, ui_stack) = match (extracted_uinodes, ui_stack.as_ref()) {
+        (Some(extracted_uinodes), Some(ui_stack)) => (extracted_uinodes, ui_stack),
+        _ => return,
+    };
+    for (stack_index, entity) in ui_stack.uinodes.iter().enumerate() {
+        let (node, tilemap, view, scaling, texture_atlas_handle, transform, visibility, clip) =
+            match tilemap_query.get(*entity) {
+                Ok(item) => item,
+                Err(_) => continue,
+            };
+        if !visibility.is_visible() {
+            continue;
+        }
+        let texture_atlas = match texture_atlases.get(texture_atlas_handle) {
+            Some(texture_atlas) => texture_atlas,
+            None => continue,
+        };
+        extract_ui_tilemap_nodes(
+            &mut extracted_uinodes.uinodes,
+            stack_index,
+            node,
+            tilemap,
+            view,
+            *scaling,
+            texture_atlas,
+            transform,
+            clip,
+        );
+    }
+}
+
+/// Push a UI node for each drawn tile of a tilemap, clipped to the tilemap's node
+#[allow(clippy::too_many_arguments)]
+fn extract_ui_tilemap_nodes(
+    uinodes: &mut Vec<ExtractedUiNode>,
+    stack_index: usize,
+    node: &Node,
+    tilemap: &Tilemap<TextureAtlasTile>,
+    view: &TilemapView,
+    scaling: UiTilemapScaling,
+    texture_atlas: &TextureAtlas,
+    transform: &GlobalTransform,
+    clip: Option<&CalculatedClip>,
+) {
+    if node.size.cmple(Vec2::ZERO).any() {
+        return;
+    }
+    let window = view.window([tilemap.width(), tilemap.height()]);
+    let (cell_size, grid_min) = scaling.cell_layout(node.size, window.layout);
+    let centre = transform.translation().truncate();
+    let node_rect = Rect::from_center_size(centre, node.size);
+    let clip = Some(match clip {
+        Some(clip) => node_rect.intersect(clip.clip),
+        None => node_rect,
+    });
+    for j in 0..window.height {
+        let y = (window.y + j) % tilemap.height();
+        for i in 0..window.width {
+            let x = (window.x + i) % tilemap.width();
+            let tile = &tilemap[[x, y]];
+            let rect = match texture_atlas.textures.get(tile.index) {
+                Some(&rect) => rect,
+                None => continue,
+            };
+            let cell_centre =
+                grid_min + (window.origin + vec2(i as f32, j as f32) + 0.5) * cell_size;
+            // a negative scale would mirror the tile, but bevy_ui culls nodes of negative size
+            let scale = cell_size / rect.size();
+            uinodes.push(ExtractedUiNode {
+                stack_index,
+                transform: transform.compute_matrix()
+                    * Mat4::from_scale_rotation_translation(
+                        scale.extend(1.),
+                        Quat::IDENTITY,
+                        cell_centre.extend(0.),
+                    ),
+                background_color: tile.color,
+                rect,
+                image: texture_atlas.texture.clone_weak(),
+                atlas_size: Some(texture_atlas.size),
+                clip,
+            });
+        }
+    }
+}
+
+/// Draws [`UiTilemapBundle`]s
+pub struct UiTilemapPlugin;
+
+impl Plugin for UiTilemapPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<UiTilemapScaling>();
+        if let Ok(render_app) = app.get_sub_app_mut(RenderApp) {
+            render_app.add_system_to_stage(
+                RenderStage::Extract,
+                extract_ui_tilemaps.after(RenderUiSystem::ExtractNode),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The check bevy_ui's `prepare_uinodes` skips unrotated nodes with
+    fn is_culled(uinode: &ExtractedUiNode) -> bool {
+        let rect_size = uinode.rect.size().extend(1.);
+        let [bottom_left, bottom_right, top_right] =
+            [vec2(-0.5, -0.5), vec2(0.5, -0.5), vec2(0.5, 0.5)].map(|corner| {
+                uinode
+                    .transform
+                    .transform_point3(corner.extend(0.) * rect_size)
+                    .truncate()
+            });
+        let clip = uinode.clip.unwrap();
+        let diff_bottom_left = (clip.min - bottom_left).max(Vec2::ZERO);
+        let diff_bottom_right = vec2(
+            (clip.max.x - bottom_right.x).min(0.),
+            (clip.min.y - bottom_right.y).max(0.),
+        );
+        let diff_top_right = (clip.max - top_right).min(Vec2::ZERO);
+        let size = uinode.transform.transform_vector3(rect_size);
+        diff_bottom_left.x - diff_bottom_right.x >= size.x
+            || diff_bottom_right.y - diff_top_right.y >= size.y
+    }
+
+    #[test]
+    fn flipped_tiles_are_drawn() {
+        let mut texture_atlas = TextureAtlas::new_empty(Handle::default(), vec2(16., 16.));
+        texture_atlas.add_texture(Rect::new(0., 0., 16., 16.));
+        let tilemap = Tilemap::from_fn(2, 1, |x, _| TextureAtlasTile {
+            flip_x: x == 0,
+            flip_y: x == 1,
+            ..TextureAtlasTile::new(0)
+        });
+        let node = Node {
+            size: vec2(40., 20.),
+        };
+        let transform = GlobalTransform::from_xyz(100., 50., 0.);
+        let extract = |clip: Option<&CalculatedClip>| {
+            let mut uinodes = vec![];
+            extract_ui_tilemap_nodes(
+                &mut uinodes,
+                0,
+                &node,
+                &tilemap,
+                &TilemapView::All,
+                UiTilemapScaling::Stretch,
+                &texture_atlas,
+                &transform,
+                clip,
+            );
+            uinodes
+        };
+
+        let uinodes = extract(None);
+        assert_eq!(uinodes.len(), 2);
+        for (uinode, centre) in uinodes.iter().zip([vec2(90., 50.), vec2(110., 50.)]) {
+            let size = uinode
+                .transform
+                .transform_vector3(uinode.rect.size().extend(1.));
+            assert_eq!(size.truncate(), vec2(20., 20.));
+            assert_eq!(
+                uinode.transform.transform_point3(Vec3::ZERO).truncate(),
+                centre
+            );
+            assert!(!is_culled(uinode));
+        }
+
+        let clip = CalculatedClip {
+            clip: Rect::new(100., 0., 200., 100.),
+        };
+        let uinodes = extract(Some(&clip));
+        assert!(is_culled(&uinodes[0]));
+        assert!(!is_culled(&uinodes[1]));
+    }
+
+    #[test]
+    fn cell_layout() {
+        let (cell_size, grid_min) = UiTilemapScaling::Fit.cell_layout(vec2(200., 100.), [4, 4]);
+        assert_eq!(cell_size, vec2(25., 25.));
+        assert_eq!(grid_min, vec2(-50., -50.));
+
+        let (cell_size, grid_min) = UiTilemapScaling::Stretch.cell_layout(vec2(200., 100.), [4, 4]);
+        assert_eq!(cell_size, vec2(50., 25.));
+        assert_eq!(grid_min, vec2(-100., -50.));
+    }
+}
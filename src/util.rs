@@ -1,35 +1,70 @@
 use geometry::TilemapGeometry;
+use geometry::TilemapView;
 
+use crate::cell_sizes::TilemapCellSizes;
 use crate::geometry;
 use bevy::prelude::*;
 
+/// Find the cell of a tilemap drawn under a point in world space.
+///
+/// Only the grid geometry is considered, so tiles drawn with an offset or scale
+/// are picked by the cell they belong to. Points in the spacing between cells pick nothing.
+/// Tilemaps with [`TilemapCellSizes`] are picked by the sizes of their cells.
 pub fn pick_tile(
     world_point: Vec2,
     transform: &GlobalTransform,
     width: usize,
-    mheight: usize,
-    geometry: TilemapGeometry,
-) {
+    height: usize,
+    geometry: &TilemapGeometry,
+    view: &TilemapView,
+    cell_sizes: Option<&TilemapCellSizes>,
+) -> Option<[usize; 2]> {
+    let window = view.window([width, height]);
+    if let Some(cell_sizes) = cell_sizes {
+        let [x, y] = cell_sizes.pick(world_point, transform, geometry)?;
+        // views select the cells drawn without moving them
+        let drawn = |cell: usize, first: usize, length: usize, size: usize| {
+            cell < size && (cell + size - first % size) % size < length
+        };
+        return (drawn(x, window.x, window.width, width)
+            && drawn(y, window.y, window.height, height))
+        .then_some([x, y]);
+    }
     let grid_space_point = transform
         .affine()
-        .transform_vector3(world_point.extend(0.0));
+        .inverse()
+        .transform_point3(world_point.extend(0.0))
+        .truncate();
+    let cell = geometry.local_to_cell(window.layout, grid_space_point) - window.origin;
+    if !geometry.is_within_cell(cell) {
+        return None;
+    }
+    let cell = cell + 0.5;
+    if (0.0..window.width as f32).contains(&cell.x) && (0.0..window.height as f32).contains(&cell.y)
+    {
+        Some([
+            (window.x + cell.x as usize) % width,
+            (window.y + cell.y as usize) % height,
+        ])
+    } else {
+        None
+    }
+}
 
-    // let grid_dimensions = vec2(width as f32, height as f32);
-    // let grid_size = grid_dimensions * geometry.tile_size;
-    // let grid_translation = transform.affine().transform_vector3(
-    //     (0.5 * geometry.tile_size - (0.5 + geometry.anchor.as_vec()) * grid_size
-    //         + vec2(
-    //             if geometry.reverse_rows {
-    //                 grid_size.x - geometry.tile_size.x
-    //             } else {
-    //                 0.0
-    //             },
-    //             if geometry.reverse_columns {
-    //                 grid_size.y - geometry.tile_size.y
-    //             } else {
-    //                 0.0
-    //             },
-    //         ))
-    //     .extend(0.),
-    // );
+/// The position in world space of the point `cell` of a tilemap, in cell coordinates
+/// with cell centres at whole numbered coordinates.
+/// The inverse of [`pick_tile`], extended to points outside of the cells drawn by `view`
+/// where the grid would continue.
+pub fn cell_to_world(
+    cell: Vec2,
+    transform: &GlobalTransform,
+    width: usize,
+    height: usize,
+    geometry: &TilemapGeometry,
+    view: &TilemapView,
+) -> Vec3 {
+    let window = view.window([width, height]);
+    let window_cell = cell - Vec2::new(window.x as f32, window.y as f32);
+    let local = geometry.cell_to_local(window.layout, window.origin + window_cell);
+    transform.transform_point(local.extend(0.0))
 }
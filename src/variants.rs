@@ -0,0 +1,235 @@
+use crate::tile::TextureAtlasTile;
+use crate::tile::Tileable;
+
+/// Interchangeable tiles with weights, such as the images of a grass tile,
+/// picked between by a hash of each cell's coordinates.
+///
+/// The same seed and coordinates always pick the same variant,
+/// so painted cells keep their variant when the tilemap is saved and loaded again.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(try_from = "TileVariantsData<T>")
+)]
+pub struct TileVariants<T> {
+    variants: Vec<T>,
+    /// running totals of the weights
+    cumulative_weights: Vec<u64>,
+}
+
+/// Deserialized [`TileVariants`] with weights not yet checked against its variants
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct TileVariantsData<T> {
+    variants: Vec<T>,
+    cumulative_weights: Vec<u64>,
+}
+
+#[cfg(feature = "serde")]
+impl<T> TryFrom<TileVariantsData<T>> for TileVariants<T> {
+    type Error = String;
+
+    fn try_from(data: TileVariantsData<T>) -> Result<Self, Self::Error> {
+        let TileVariantsData {
+            variants,
+            cumulative_weights,
+        } = data;
+        if variants.len() != cumulative_weights.len() {
+            return Err(format!(
+                "{} weights don't match {} variants",
+                cumulative_weights.len(),
+                variants.len()
+            ));
+        }
+        let mut previous = 0;
+        for &total in &cumulative_weights {
+            match total.checked_sub(previous) {
+                Some(weight) if weight <= u32::MAX as u64 => previous = total,
+                _ => return Err(format!("weight from {previous} to {total} is out of range")),
+            }
+        }
+        Ok(Self {
+            variants,
+            cumulative_weights,
+        })
+    }
+}
+
+impl<T> TileVariants<T> {
+    /// Variants paired with their weights. Variants with a weight of zero are never picked.
+    pub fn new(variants: impl IntoIterator<Item = (T, u32)>) -> Self {
+        let mut total = 0;
+        let (variants, cumulative_weights) = variants
+            .into_iter()
+            .map(|(variant, weight)| {
+                total += weight as u64;
+                (variant, total)
+            })
+            .unzip();
+        Self {
+            variants,
+            cumulative_weights,
+        }
+    }
+
+    /// Variants with equal weights
+    pub fn uniform(variants: impl IntoIterator<Item = T>) -> Self {
+        Self::new(variants.into_iter().map(|variant| (variant, 1)))
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.variants.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.variants.is_empty()
+    }
+
+    /// Sum of the weights of the variants
+    #[inline]
+    pub fn total_weight(&self) -> u64 {
+        self.cumulative_weights.last().copied().unwrap_or(0)
+    }
+
+    /// The variants with their weights
+    pub fn iter(&self) -> impl Iterator<Item = (&T, u32)> {
+        self.variants
+            .iter()
+            .zip(self.cumulative_weights.iter())
+            .scan(0, |previous, (variant, &total)| {
+                let weight = (total - *previous) as u32;
+                *previous = total;
+                Some((variant, weight))
+            })
+    }
+
+    /// The variant for the cell `[x, y]`, or `None` if the total weight is zero
+    pub fn pick(&self, seed: u64, cell: [usize; 2]) -> Option<&T> {
+        let total = self.total_weight();
+        if total == 0 {
+            return None;
+        }
+        let roll = cell_hash(seed, cell) % total;
+        let index = self
+            .cumulative_weights
+            .partition_point(|&cumulative| cumulative <= roll);
+        self.variants.get(index)
+    }
+}
+
+impl<T> TileVariants<T>
+where
+    T: Tileable,
+{
+    /// Picks the tile for each cell for the painting functions of
+    /// [`Tilemap`](crate::tilemap::Tilemap) that take a closure,
+    /// with the default tile if there is nothing to pick
+    pub fn painter(&self, seed: u64) -> impl Fn(usize, usize) -> T + '_ {
+        move |x, y| self.pick(seed, [x, y]).cloned().unwrap_or_default()
+    }
+}
+
+impl TileVariants<TextureAtlasTile> {
+    /// Variants of the images at `index` in a texture atlas paired with their weights
+    pub fn from_indices(indices: impl IntoIterator<Item = (usize, u32)>) -> Self {
+        Self::new(
+            indices
+                .into_iter()
+                .map(|(index, weight)| (TextureAtlasTile::new(index), weight)),
+        )
+    }
+}
+
+/// Deterministic hash of a cell's coordinates, the same on every platform and run
+pub fn cell_hash(seed: u64, [x, y]: [usize; 2]) -> u64 {
+    let hash = split_mix(seed ^ x as u64);
+    split_mix(hash ^ (y as u64).rotate_left(32))
+}
+
+/// The SplitMix64 finalizer
+#[inline]
+fn split_mix(mut z: u64) -> u64 {
+    z = z.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tilemap::Tilemap;
+    use bevy::math::ivec2;
+
+    #[derive(Clone, Debug, Default, PartialEq)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    struct Cell(usize);
+
+    impl Tileable for Cell {}
+
+    #[test]
+    fn picks_follow_weights() {
+        let variants = TileVariants::new([(Cell(1), 6), (Cell(2), 0), (Cell(3), 2)]);
+        assert_eq!(variants.total_weight(), 8);
+        assert_eq!(
+            variants
+                .iter()
+                .map(|(_, weight)| weight)
+                .collect::<Vec<_>>(),
+            vec![6, 0, 2]
+        );
+        let mut counts = [0; 4];
+        for y in 0..100 {
+            for x in 0..100 {
+                counts[variants.pick(7, [x, y]).unwrap().0] += 1;
+            }
+        }
+        assert_eq!(counts[2], 0);
+        assert!((7_000..8_000).contains(&counts[1]), "{counts:?}");
+        assert!((2_000..3_000).contains(&counts[3]), "{counts:?}");
+
+        assert_eq!(TileVariants::<Cell>::default().pick(0, [0, 0]), None);
+        assert_eq!(TileVariants::new([(Cell(1), 0)]).pick(0, [0, 0]), None);
+    }
+
+    #[test]
+    fn painting_is_stable() {
+        let variants = TileVariants::uniform((0..4).map(Cell));
+        let mut a = Tilemap::<Cell>::from_default(16, 16);
+        let mut b = a.clone();
+        a.fill_rect_fn(ivec2(0, 0), ivec2(15, 15), variants.painter(42));
+        b.fill_rect_fn(ivec2(8, 8), ivec2(15, 15), variants.painter(42));
+        b.fill_rect_fn(ivec2(0, 0), ivec2(15, 15), variants.painter(42));
+        assert!((&a).into_iter().eq(&b));
+
+        let mut c = Tilemap::<Cell>::from_default(16, 16);
+        c.fill_rect_fn(ivec2(0, 0), ivec2(15, 15), variants.painter(43));
+        assert!(!(&a).into_iter().eq(&c));
+        assert!((0..4).all(|i| (&a).into_iter().any(|cell| cell.0 == i)));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_round_trip() {
+        let variants = TileVariants::new([(Cell(1), 6), (Cell(2), 0), (Cell(3), 2)]);
+        let json = serde_json::to_string(&variants).unwrap();
+        let deserialized: TileVariants<Cell> = serde_json::from_str(&json).unwrap();
+        assert_eq!(deserialized, variants);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn invalid_weights_are_rejected() {
+        let parse = |json: &str| serde_json::from_str::<TileVariants<Cell>>(json);
+        assert!(parse(r#"{"variants":[1,2],"cumulative_weights":[3]}"#).is_err());
+        assert!(parse(r#"{"variants":[1],"cumulative_weights":[3,4]}"#).is_err());
+        assert!(parse(r#"{"variants":[1,2],"cumulative_weights":[3,2]}"#).is_err());
+        let json = format!(r#"{{"variants":[1],"cumulative_weights":[{}]}}"#, u64::MAX);
+        assert!(parse(&json).is_err());
+        assert!(parse(r#"{"variants":[1,2],"cumulative_weights":[3,3]}"#).is_ok());
+        assert!(parse(r#"{"variants":[],"cumulative_weights":[]}"#).is_ok());
+    }
+}
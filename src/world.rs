@@ -0,0 +1,445 @@
+use crate::cell_sizes::TilemapCellSizes;
+use crate::geometry::TilemapGeometry;
+use crate::geometry::TilemapView;
+use crate::indexing::IndexableGrid;
+use crate::tile::Tileable;
+use crate::tilemap::Tilemap;
+use crate::util::pick_tile;
+use bevy::ecs::system::SystemParam;
+use bevy::math::ivec2;
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+/// Offsets to the four cells sharing an edge with a cell
+pub const ORTHOGONAL_NEIGHBORS: [IVec2; 4] = [
+    IVec2::new(1, 0),
+    IVec2::new(0, 1),
+    IVec2::new(-1, 0),
+    IVec2::new(0, -1),
+];
+
+/// Position of a tilemap in a world grid shared by several tilemaps.
+/// The tilemap's cell `[x, y]` is the world cell `origin + [x, y]`.
+#[derive(Clone, Copy, Component, Debug, Default, PartialEq, Eq, Reflect)]
+#[reflect(Component)]
+pub struct TilemapPlacement {
+    pub origin: IVec2,
+    /// where tilemaps overlap, cells are taken from the tilemap with the highest order
+    pub order: i32,
+}
+
+impl TilemapPlacement {
+    pub fn new(origin: IVec2) -> Self {
+        Self { origin, order: 0 }
+    }
+
+    #[inline]
+    pub fn with_order(mut self, order: i32) -> Self {
+        self.order = order;
+        self
+    }
+
+    /// The tilemap's cell at the world cell `cell`, if it is within a tilemap with dimensions `map_size`
+    #[inline]
+    pub fn local_cell(&self, map_size: [usize; 2], cell: IVec2) -> Option<[usize; 2]> {
+        let x = usize::try_from(cell.x as i64 - self.origin.x as i64).ok()?;
+        let y = usize::try_from(cell.y as i64 - self.origin.y as i64).ok()?;
+        if x < map_size[0] && y < map_size[1] {
+            Some([x, y])
+        } else {
+            None
+        }
+    }
+
+    /// The world cell of the tilemap's cell `[x, y]`
+    #[inline]
+    pub fn world_cell(&self, [x, y]: [usize; 2]) -> IVec2 {
+        self.origin + ivec2(x as i32, y as i32)
+    }
+
+    /// True if the tilemaps with these placements and dimensions share any world cells
+    fn overlaps(&self, map_size: [usize; 2], other: &Self, other_size: [usize; 2]) -> bool {
+        (0..2).all(|axis| {
+            let start = self.origin[axis] as i64;
+            let other_start = other.origin[axis] as i64;
+            start < other_start + other_size[axis] as i64
+                && other_start < start + map_size[axis] as i64
+        })
+    }
+}
+
+/// The placements of the tilemaps of a [`TilemapWorld`] from the highest priority,
+/// for finding the tilemaps of many world cells
+struct PlacementLookup {
+    placements: Vec<(Entity, TilemapPlacement, [usize; 2])>,
+    /// for each placement, the placements of higher priority overlapping it
+    covered_by: Vec<Vec<usize>>,
+    /// the placement of the most recently located cell, likely to contain the next
+    last: std::cell::Cell<usize>,
+}
+
+impl PlacementLookup {
+    fn new(mut placements: Vec<(Entity, TilemapPlacement, [usize; 2])>) -> Self {
+        placements.sort_by_key(|&(entity, placement, _)| Reverse((placement.order, entity)));
+        let covered_by = placements
+            .iter()
+            .enumerate()
+            .map(|(i, (_, placement, size))| {
+                (0..i)
+                    .filter(|&j| {
+                        let (_, other, other_size) = &placements[j];
+                        placement.overlaps(*size, other, *other_size)
+                    })
+                    .collect()
+            })
+            .collect();
+        Self {
+            placements,
+            covered_by,
+            last: std::cell::Cell::new(0),
+        }
+    }
+
+    #[inline]
+    fn local_cell(&self, i: usize, cell: IVec2) -> Option<[usize; 2]> {
+        let (_, placement, size) = &self.placements[i];
+        placement.local_cell(*size, cell)
+    }
+
+    /// The tilemap entity containing the world cell `cell` and the cell's coordinates in that tilemap
+    fn locate(&self, cell: IVec2) -> Option<(Entity, [usize; 2])> {
+        let last = self.last.get();
+        if let Some(local) = self
+            .placements
+            .get(last)
+            .and_then(|_| self.local_cell(last, cell))
+        {
+            if self.covered_by[last]
+                .iter()
+                .all(|&i| self.local_cell(i, cell).is_none())
+            {
+                return Some((self.placements[last].0, local));
+            }
+        }
+        let (i, local) =
+            (0..self.placements.len()).find_map(|i| Some((i, self.local_cell(i, cell)?)))?;
+        self.last.set(i);
+        Some((self.placements[i].0, local))
+    }
+}
+
+/// Access to every `Tilemap<T>` with a [`TilemapPlacement`] through world cell coordinates.
+///
+/// Where tilemaps overlap, the cell is taken from the tilemap with the highest
+/// [`TilemapPlacement::order`], or if their orders are equal from the greater entity.
+#[derive(SystemParam)]
+pub struct TilemapWorld<'w, 's, T>
+where
+    T: Tileable,
+{
+    #[allow(clippy::type_complexity)]
+    tilemaps: Query<
+        'w,
+        's,
+        (
+            Entity,
+            &'static mut Tilemap<T>,
+            &'static TilemapPlacement,
+            Option<&'static TilemapGeometry>,
+            Option<&'static TilemapView>,
+            Option<&'static TilemapCellSizes>,
+            Option<&'static GlobalTransform>,
+        ),
+    >,
+}
+
+impl<'w, 's, T> TilemapWorld<'w, 's, T>
+where
+    T: Tileable,
+{
+    fn lookup(&self) -> PlacementLookup {
+        PlacementLookup::new(
+            self.tilemaps
+                .iter()
+                .map(|(entity, tilemap, placement, ..)| {
+                    (entity, *placement, [tilemap.width(), tilemap.height()])
+                })
+                .collect(),
+        )
+    }
+
+    /// The tilemap entity containing the world cell `cell` and the cell's coordinates in that tilemap
+    pub fn locate(&self, cell: IVec2) -> Option<(Entity, [usize; 2])> {
+        self.tilemaps
+            .iter()
+            .filter_map(|(entity, tilemap, placement, ..)| {
+                let local = placement.local_cell([tilemap.width(), tilemap.height()], cell)?;
+                Some(((placement.order, entity), local))
+            })
+            .max_by_key(|&(priority, _)| priority)
+            .map(|((_, entity), local)| (entity, local))
+    }
+
+    fn tile(&self, (entity, local): (Entity, [usize; 2])) -> Option<&T> {
+        let (_, tilemap, ..) = self.tilemaps.get(entity).ok()?;
+        Some(&tilemap[local])
+    }
+
+    /// The tile at the world cell `cell`
+    pub fn get(&self, cell: IVec2) -> Option<&T> {
+        self.tile(self.locate(cell)?)
+    }
+
+    /// Replace the tile at the world cell `cell`, returning the previous tile,
+    /// or `None` if the cell is not within any tilemap
+    pub fn set(&mut self, cell: IVec2, tile: T) -> Option<T> {
+        let (entity, local) = self.locate(cell)?;
+        let (_, mut tilemap, ..) = self.tilemaps.get_mut(entity).ok()?;
+        Some(std::mem::replace(&mut tilemap[local], tile))
+    }
+
+    /// The world cells sharing an edge with `cell` that are within a tilemap, with their tiles
+    pub fn neighbors(&self, cell: IVec2) -> impl Iterator<Item = (IVec2, &T)> {
+        ORTHOGONAL_NEIGHBORS.into_iter().filter_map(move |offset| {
+            let neighbor = offset_cell(cell, offset)?;
+            Some((neighbor, self.get(neighbor)?))
+        })
+    }
+
+    /// Every cell of every tilemap with its world cell coordinates
+    pub fn iter(&self) -> impl Iterator<Item = (IVec2, &T)> {
+        self.tilemaps
+            .iter()
+            .flat_map(|(_, tilemap, placement, ..)| {
+                tilemap
+                    .indexed_iter()
+                    .map(move |(x, y, tile)| (placement.world_cell([x, y]), tile))
+            })
+    }
+
+    /// The world cell drawn under a point in world space.
+    /// Where tilemaps overlap the cell of the tilemap drawn in front is returned.
+    pub fn pick(&self, world_point: Vec2) -> Option<IVec2> {
+        self.tilemaps
+            .iter()
+            .filter_map(
+                |(_, tilemap, placement, geometry, view, cell_sizes, transform)| {
+                    let transform = transform?;
+                    let cell = pick_tile(
+                        world_point,
+                        transform,
+                        tilemap.width(),
+                        tilemap.height(),
+                        geometry?,
+                        view?,
+                        cell_sizes,
+                    )?;
+                    Some((transform.translation().z, placement.world_cell(cell)))
+                },
+            )
+            .max_by(|(a, _), (b, _)| a.total_cmp(b))
+            .map(|(_, cell)| cell)
+    }
+
+    /// The shortest path of orthogonal steps from `start` to `goal`, including both,
+    /// through world cells whose tiles satisfy `passable`.
+    /// Returns `None` if there is no path.
+    pub fn find_path(
+        &self,
+        start: IVec2,
+        goal: IVec2,
+        mut passable: impl FnMut(IVec2, &T) -> bool,
+    ) -> Option<Vec<IVec2>> {
+        let lookup = self.lookup();
+        find_path(start, goal, |cell| {
+            lookup
+                .locate(cell)
+                .and_then(|located| self.tile(located))
+                .is_some_and(|tile| passable(cell, tile))
+        })
+    }
+}
+
+/// `cell` moved by `offset`, or `None` if that is outside of the range of cell coordinates
+#[inline]
+fn offset_cell(cell: IVec2, offset: IVec2) -> Option<IVec2> {
+    Some(ivec2(
+        cell.x.checked_add(offset.x)?,
+        cell.y.checked_add(offset.y)?,
+    ))
+}
+
+/// A* search for the shortest path of orthogonal steps from `start` to `goal`,
+/// including both, through cells that are `passable`.
+/// Returns `None` if there is no path, which is only found out once every reachable cell
+/// has been searched, so only finitely many cells should be `passable`.
+pub fn find_path(
+    start: IVec2,
+    goal: IVec2,
+    mut passable: impl FnMut(IVec2) -> bool,
+) -> Option<Vec<IVec2>> {
+    if !passable(start) || !passable(goal) {
+        return None;
+    }
+    let heuristic = |cell: IVec2| {
+        (goal.x as i64 - cell.x as i64).unsigned_abs()
+            + (goal.y as i64 - cell.y as i64).unsigned_abs()
+    };
+    let mut came_from: HashMap<IVec2, IVec2> = HashMap::default();
+    let mut cost: HashMap<IVec2, u64> = HashMap::default();
+    let mut open = BinaryHeap::new();
+    cost.insert(start, 0);
+    open.push(Reverse((heuristic(start), 0, start.to_array())));
+    while let Some(Reverse((_, steps, cell))) = open.pop() {
+        let cell = IVec2::from(cell);
+        if cell == goal {
+            let mut path = vec![goal];
+            let mut current = goal;
+            while let Some(&previous) = came_from.get(&current) {
+                path.push(previous);
+                current = previous;
+            }
+            path.reverse();
+            return Some(path);
+        }
+        if cost.get(&cell).is_some_and(|&best| best < steps) {
+            continue;
+        }
+        for offset in ORTHOGONAL_NEIGHBORS {
+            let next = match offset_cell(cell, offset) {
+                Some(next) => next,
+                None => continue,
+            };
+            let next_steps = steps + 1;
+            if cost.get(&next).is_some_and(|&best| best <= next_steps) || !passable(next) {
+                continue;
+            }
+            cost.insert(next, next_steps);
+            came_from.insert(next, cell);
+            open.push(Reverse((
+                next_steps + heuristic(next),
+                next_steps,
+                next.to_array(),
+            )));
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::ecs::system::SystemState;
+
+    #[derive(Clone, Debug, Default, PartialEq)]
+    struct Cell(bool);
+
+    impl Tileable for Cell {}
+
+    #[test]
+    fn path_around_wall() {
+        let wall = |cell: IVec2| cell.x == 2 && cell.y < 4;
+        let path = find_path(ivec2(0, 0), ivec2(4, 0), |cell| {
+            (0..8).contains(&cell.x) && (0..8).contains(&cell.y) && !wall(cell)
+        })
+        .unwrap();
+        assert_eq!(path.first(), Some(&ivec2(0, 0)));
+        assert_eq!(path.last(), Some(&ivec2(4, 0)));
+        assert_eq!(path.len(), 13);
+        for step in path.windows(2) {
+            let step = (step[1] - step[0]).abs();
+            assert_eq!(step.x + step.y, 1);
+            assert!(!wall(step[1]));
+        }
+        assert_eq!(
+            find_path(ivec2(0, 0), ivec2(2, 0), |cell| !wall(cell)),
+            None
+        );
+    }
+
+    #[test]
+    fn cells_across_tilemap_borders() {
+        let mut world = World::new();
+        world.spawn((
+            Tilemap::from_elem(3, 2, Cell(true)),
+            TilemapPlacement::new(ivec2(-3, 0)),
+        ));
+        world.spawn((
+            Tilemap::from_fn(2, 2, |x, _| Cell(x == 1)),
+            TilemapPlacement::new(ivec2(0, 0)),
+        ));
+        let mut state: SystemState<TilemapWorld<Cell>> = SystemState::new(&mut world);
+        let mut tilemap_world = state.get_mut(&mut world);
+
+        assert_eq!(tilemap_world.get(ivec2(-1, 1)), Some(&Cell(true)));
+        assert_eq!(tilemap_world.get(ivec2(0, 1)), Some(&Cell(false)));
+        assert_eq!(tilemap_world.get(ivec2(2, 0)), None);
+        assert_eq!(tilemap_world.locate(ivec2(-3, 0)).unwrap().1, [0, 0]);
+        assert_eq!(tilemap_world.neighbors(ivec2(-1, 0)).count(), 3);
+        assert_eq!(tilemap_world.iter().count(), 10);
+
+        let passable = |_: IVec2, cell: &Cell| cell.0;
+        assert_eq!(
+            tilemap_world.find_path(ivec2(-3, 0), ivec2(1, 0), passable),
+            None
+        );
+        assert_eq!(
+            tilemap_world.set(ivec2(0, 1), Cell(true)),
+            Some(Cell(false))
+        );
+        let path = tilemap_world
+            .find_path(ivec2(-3, 0), ivec2(1, 0), passable)
+            .unwrap();
+        assert_eq!(path.len(), 7);
+        assert!(path.contains(&ivec2(0, 1)));
+    }
+
+    #[test]
+    fn overlaps_follow_order() {
+        let mut world = World::new();
+        let high = world
+            .spawn((
+                Tilemap::from_elem(2, 2, Cell(true)),
+                TilemapPlacement::new(ivec2(1, 1)).with_order(1),
+            ))
+            .id();
+        let low = world
+            .spawn((
+                Tilemap::from_elem(4, 4, Cell(false)),
+                TilemapPlacement::new(ivec2(0, 0)),
+            ))
+            .id();
+        let mut state: SystemState<TilemapWorld<Cell>> = SystemState::new(&mut world);
+        let tilemap_world = state.get_mut(&mut world);
+
+        assert_eq!(tilemap_world.locate(ivec2(1, 1)), Some((high, [0, 0])));
+        assert_eq!(tilemap_world.locate(ivec2(0, 1)), Some((low, [0, 1])));
+        let lookup = tilemap_world.lookup();
+        for y in -1..5 {
+            for x in -1..5 {
+                let cell = ivec2(x, y);
+                assert_eq!(lookup.locate(cell), tilemap_world.locate(cell));
+            }
+        }
+        let path = tilemap_world
+            .find_path(ivec2(1, 1), ivec2(2, 2), |_, cell| cell.0)
+            .unwrap();
+        assert_eq!(path.len(), 3);
+        assert_eq!(
+            tilemap_world.find_path(ivec2(1, 1), ivec2(3, 3), |_, cell| cell.0),
+            None
+        );
+    }
+
+    #[test]
+    fn far_apart_cells() {
+        let start = ivec2(i32::MIN, i32::MIN);
+        let goal = ivec2(i32::MAX, i32::MAX);
+        assert_eq!(
+            find_path(start, goal, |cell| cell == start || cell == goal),
+            None
+        );
+    }
+}